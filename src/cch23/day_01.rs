@@ -1,33 +1,210 @@
+use std::borrow::Cow;
 use std::rc::Rc;
 use std::str::FromStr;
 
-use rocket::get;
+use num_bigint::{BigInt, ParseBigIntError};
+use num_traits::CheckedAdd;
 use rocket::http::uri::{fmt, Segments};
+use rocket::http::Status;
 use rocket::request::FromSegments;
+use rocket::serde::{json::Json, Serialize};
+use rocket::{get, FromFormField};
 
 #[derive(Debug)]
 struct SegmentsRest<T>(Rc<[T]>);
 
-impl<'r, T: FromStr<Err = Err>, Err: std::fmt::Debug> FromSegments<'r> for SegmentsRest<T> {
-    type Error = Err;
+/// Upper bound on the number of values a path may expand into.
+const MAX_SEGMENT_VALUES: usize = 10_000;
+
+#[derive(Debug, Clone, PartialEq)]
+enum SegmentsError<E> {
+    Parse(E),
+    BadStep,
+    BadRange,
+    TooManyValues,
+}
+
+impl<E> From<E> for SegmentsError<E> {
+    fn from(value: E) -> Self {
+        SegmentsError::Parse(value)
+    }
+}
+
+impl<E> SegmentsError<E> {
+    fn message(&self) -> Cow<'static, str> {
+        match self {
+            SegmentsError::Parse(_) => "Segment is not a number".into(),
+            SegmentsError::BadStep => "Range step must be positive".into(),
+            SegmentsError::BadRange => "Range start must not exceed its end".into(),
+            SegmentsError::TooManyValues => {
+                format!("Too many values, the limit is {MAX_SEGMENT_VALUES}").into()
+            }
+        }
+    }
+}
+
+/// Split `a-b` into its bounds, allowing either bound to be negative.
+fn split_dash_range(item: &str) -> Option<(&str, &str)> {
+    let (index, _) = item
+        .char_indices()
+        .skip(1)
+        .find(|&(index, c)| c == '-' && item[..index].ends_with(|c: char| c.is_ascii_digit()))?;
+
+    Some((&item[..index], &item[index + 1..]))
+}
+
+/// Expand a single path segment into `values`.
+///
+/// A segment is a comma separated list, where each item is a number, an inclusive range written
+/// `a-b` or `a..=b`, or an exclusive range written `a..b`. Ranges take an optional `:step` suffix.
+fn expand_segment<T, E>(segment: &str, values: &mut Vec<T>) -> Result<(), SegmentsError<E>>
+where
+    T: FromStr<Err = E> + Clone + PartialOrd + CheckedAdd + From<u8>,
+{
+    for item in segment.split(',') {
+        let (range, step) = match item.split_once(':') {
+            Some((range, step)) => (range, Some(step.parse::<T>()?)),
+            None => (item, None),
+        };
+        let bounds = if let Some((start, end)) = range.split_once("..=") {
+            Some((start, end, true))
+        } else if let Some((start, end)) = range.split_once("..") {
+            Some((start, end, false))
+        } else {
+            split_dash_range(range).map(|(start, end)| (start, end, true))
+        };
+
+        match (bounds, step) {
+            (Some((start, end, inclusive)), step) => {
+                let start: T = start.parse()?;
+                let end: T = end.parse()?;
+                let step = step.unwrap_or_else(|| T::from(1));
+
+                if step <= T::from(0) {
+                    return Err(SegmentsError::BadStep);
+                }
+
+                if start > end {
+                    return Err(SegmentsError::BadRange);
+                }
+
+                let mut value = Some(start);
+
+                // The range also ends where the next value would overflow `T`.
+                while let Some(current) = value.filter(|v| *v < end || (inclusive && *v == end)) {
+                    if values.len() >= MAX_SEGMENT_VALUES {
+                        return Err(SegmentsError::TooManyValues);
+                    }
+
+                    value = current.checked_add(&step);
+                    values.push(current);
+                }
+            }
+            (None, Some(_)) => return Err(SegmentsError::BadStep),
+            (None, None) => {
+                if values.len() >= MAX_SEGMENT_VALUES {
+                    return Err(SegmentsError::TooManyValues);
+                }
+
+                values.push(range.parse()?);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+impl<'r, T, Err> FromSegments<'r> for SegmentsRest<T>
+where
+    T: FromStr<Err = Err> + Clone + PartialOrd + CheckedAdd + From<u8>,
+    Err: std::fmt::Debug,
+{
+    type Error = SegmentsError<Err>;
 
     fn from_segments(segments: Segments<'r, fmt::Path>) -> Result<Self, Self::Error> {
         let mut values: Vec<T> = Vec::new();
         for segment in segments {
-            let value: T = segment.parse()?;
-            values.push(value);
+            expand_segment(segment, &mut values)?;
         }
         Ok(SegmentsRest(values.into()))
     }
 }
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, FromFormField)]
+enum FoldOp {
+    #[default]
+    Xor,
+    And,
+    Or,
+    Sum,
+    Product,
+}
+
+impl FoldOp {
+    fn identity(self) -> BigInt {
+        match self {
+            FoldOp::Xor | FoldOp::Or | FoldOp::Sum => BigInt::from(0),
+            FoldOp::And => BigInt::from(-1),
+            FoldOp::Product => BigInt::from(1),
+        }
+    }
+
+    fn apply(self, acc: BigInt, x: &BigInt) -> BigInt {
+        match self {
+            FoldOp::Xor => acc ^ x,
+            FoldOp::And => acc & x,
+            FoldOp::Or => acc | x,
+            FoldOp::Sum => acc + x,
+            FoldOp::Product => acc * x,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct SledIdError {
+    error: Cow<'static, str>,
+}
+
+type SledIdResult = Result<String, (Status, Json<SledIdError>)>;
+
+macro_rules! sled_err {
+    ($msg:expr) => {
+        (Status::BadRequest, Json(SledIdError { error: $msg.into() }))
+    };
+}
+
+/// Upper bound on the size of an exact (non-modular) result, roughly 315k decimal digits.
+const MAX_RESULT_BITS: u64 = 1 << 20;
+
 /// Handles both Task 1-1 & 1-2.
-#[get("/<nums..>")]
-fn sled_id(nums: SegmentsRest<i32>) -> String {
-    let SegmentsRest(nums) = nums;
-    let a = nums.iter().fold(0, |acc, &x| acc ^ x) as i64;
-    let a = a.pow(3);
-    a.to_string()
+///
+/// Path segments may also be ranges or lists, see [`expand_segment`].
+/// The values are folded with `op` (XOR by default) and raised to `exp` (3 by default). The result
+/// is exact, unless a `modulus` is given in which case the power is computed modulo that value.
+#[get("/<nums..>?<op>&<exp>&<modulus>")]
+fn sled_id(
+    nums: Result<SegmentsRest<BigInt>, SegmentsError<ParseBigIntError>>,
+    op: Option<FoldOp>,
+    exp: Option<u32>,
+    modulus: Option<i64>,
+) -> SledIdResult {
+    let SegmentsRest(nums) = nums.map_err(|err| sled_err!(err.message()))?;
+    let op = op.unwrap_or_default();
+    let exp = exp.unwrap_or(3);
+    let a = nums.iter().fold(op.identity(), |acc, x| op.apply(acc, x));
+
+    match modulus {
+        Some(modulus) if modulus <= 0 => Err(sled_err!("Modulus must be positive")),
+        Some(modulus) => Ok(a
+            .modpow(&BigInt::from(exp), &BigInt::from(modulus))
+            .to_string()),
+        // 0, 1 and -1 stay that small whatever the exponent.
+        None if a.bits() <= 1 => Ok(a.pow(exp).to_string()),
+        None if a.bits().saturating_mul(exp as u64) > MAX_RESULT_BITS => {
+            Err(sled_err!("Result too large, try a modulus"))
+        }
+        None => Ok(a.pow(exp).to_string()),
+    }
 }
 
 pub fn routes() -> Vec<rocket::Route> {
@@ -39,15 +216,128 @@ mod tests_day_01 {
     use super::*;
     use rstest::*;
 
+    type Segments = Result<SegmentsRest<BigInt>, SegmentsError<ParseBigIntError>>;
+
+    fn segments(values: &[&str]) -> Segments {
+        let values: Vec<BigInt> = values.iter().map(|value| value.parse().unwrap()).collect();
+        Ok(SegmentsRest(values.into()))
+    }
+
+    #[rstest]
+    #[case(&["4", "8"], "1728")]
+    #[case(&["10"], "1000")]
+    #[case(&["4", "5", "8", "10"], "27")]
+    #[case(&["1", "2", "4", "8"], "3375")]
+    #[case(&["2147483647"], "9903520300447984150353281023")]
+    #[case(
+        &["18446744073709551615"],
+        "6277101735386680762814942322444851025767571854389858533375"
+    )]
+    #[case(
+        &["170141183460469231731687303715884105727", "1"],
+        "4925250774549309901534880012517951725461279274952206540358180198017683359659288043629848066582208326733549413597176"
+    )]
+    #[case(
+        &["-340282366920938463463374607431768211456"],
+        "-39402006196394479212279040100143613805079739270465446667948293404245721771497210611414266254884915640806627990306816"
+    )]
+    fn test_sled_id(#[case] values: &[&str], #[case] expected: &str) {
+        let result = sled_id(segments(values), None, None, None).unwrap();
+
+        assert_eq!(result, expected);
+    }
+
+    #[rstest]
+    #[case(&["4", "8"], FoldOp::Sum, 2, None, "144")]
+    #[case(&["3", "5"], FoldOp::Product, 1, None, "15")]
+    #[case(&["6", "3"], FoldOp::And, 3, None, "8")]
+    #[case(&["4", "1"], FoldOp::Or, 2, None, "25")]
+    #[case(&["-7"], FoldOp::Xor, 3, Some(10), "7")]
+    #[case(&["2147483647", "2147483647"], FoldOp::Product, 3, Some(1_000_000_007), "959223893")]
+    #[case(
+        &["18446744073709551615", "18446744073709551615"],
+        FoldOp::Sum,
+        2,
+        None,
+        "1361129467683753853705924477137396432900"
+    )]
+    fn test_sled_id_options(
+        #[case] values: &[&str],
+        #[case] op: FoldOp,
+        #[case] exp: u32,
+        #[case] modulus: Option<i64>,
+        #[case] expected: &str,
+    ) {
+        let result = sled_id(segments(values), Some(op), Some(exp), modulus).unwrap();
+
+        assert_eq!(result, expected);
+    }
+
     #[rstest]
-    #[case(vec![4, 8], "1728")]
-    #[case(vec![10], "1000")]
-    #[case(vec![4, 5, 8, 10], "27")]
-    #[case(vec![1, 2, 4, 8], "3375")]
-    fn test_sled_id(#[case] values: Vec<i32>, #[case] expected: &str) {
-        let segments = SegmentsRest(values.into());
-        let result = sled_id(segments);
+    #[case(&["1"], u32::MAX, "1")]
+    #[case(&["-1"], u32::MAX, "-1")]
+    #[case(&["-1"], u32::MAX - 1, "1")]
+    #[case(&["0"], u32::MAX, "0")]
+    #[case(&["5", "5"], u32::MAX, "0")]
+    fn test_sled_id_unit_base(#[case] values: &[&str], #[case] exp: u32, #[case] expected: &str) {
+        let result = sled_id(segments(values), None, Some(exp), None).unwrap();
 
         assert_eq!(result, expected);
     }
+
+    #[rstest]
+    #[case(&["4"], None, Some(0))]
+    #[case(&["18446744073709551615"], Some(u32::MAX), None)]
+    fn test_sled_id_bad_request(
+        #[case] values: &[&str],
+        #[case] exp: Option<u32>,
+        #[case] modulus: Option<i64>,
+    ) {
+        let (status, _) = sled_id(segments(values), None, exp, modulus).unwrap_err();
+
+        assert_eq!(status, Status::BadRequest);
+    }
+
+    #[rstest]
+    #[case("4", vec![4])]
+    #[case("-4", vec![-4])]
+    #[case("4,8,10", vec![4, 8, 10])]
+    #[case("1-5", vec![1, 2, 3, 4, 5])]
+    #[case("-3--1", vec![-3, -2, -1])]
+    #[case("-1-1", vec![-1, 0, 1])]
+    #[case("1..5", vec![1, 2, 3, 4])]
+    #[case("1..=100:25", vec![1, 26, 51, 76])]
+    #[case("0-10:5,20", vec![0, 5, 10, 20])]
+    #[case("3..3", vec![])]
+    #[case("2147483646-2147483647", vec![2147483646, 2147483647])]
+    #[case("2147483640..=2147483647:5", vec![2147483640, 2147483645])]
+    fn test_expand_segment(#[case] segment: &str, #[case] expected: Vec<i32>) {
+        let mut values: Vec<i32> = Vec::new();
+        expand_segment(segment, &mut values).unwrap();
+
+        assert_eq!(values, expected);
+    }
+
+    #[rstest]
+    #[case("x", SegmentsError::Parse(()))]
+    #[case("1..=10:0", SegmentsError::BadStep)]
+    #[case("4:2", SegmentsError::BadStep)]
+    #[case("10-1", SegmentsError::BadRange)]
+    #[case("1..=10001", SegmentsError::TooManyValues)]
+    #[case("0..9999,1,2", SegmentsError::TooManyValues)]
+    fn test_expand_segment_error(#[case] segment: &str, #[case] expected: SegmentsError<()>) {
+        let mut values: Vec<i32> = Vec::new();
+        let err = expand_segment(segment, &mut values).unwrap_err();
+
+        assert_eq!(err.message(), expected.message());
+    }
+
+    #[test]
+    fn test_sled_id_segments_error() {
+        let nums = Err(SegmentsError::TooManyValues);
+        let (status, Json(err)) = sled_id(nums, None, None, None).unwrap_err();
+
+        assert_eq!(status, Status::BadRequest);
+        assert_eq!(err.error, "Too many values, the limit is 10000");
+    }
 }
@@ -1,7 +1,21 @@
-use rocket::post;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use indexmap::IndexMap;
+use rocket::data::{self, Data, FromData, Limits};
+use rocket::http::Status;
+use rocket::request::local_cache;
+use rocket::response::{Responder, Response};
 use rocket::serde::{json::Json, Deserialize, Serialize};
+use rocket::{delete, get, post, put, FromFormField, Request, State};
+use serde_json::{Map, Value};
+use sqlx::{FromRow, PgConnection, QueryBuilder};
+
+use crate::cch23::GiftDatabase;
 
-#[derive(Clone, Debug, Deserialize, Default)]
+#[derive(Clone, Debug, Deserialize, Serialize, Default)]
 #[serde(default)]
 struct Reindeer<'r> {
     name: &'r str,
@@ -15,56 +29,622 @@ struct Reindeer<'r> {
     candies_eaten_yesterday: i32,
 }
 
+fn team_strength(team: &[Reindeer<'_>]) -> i32 {
+    team.iter().map(|reindeer| reindeer.strength).sum()
+}
+
 // TODO not sure if the return type is correct. Might need to be Json<String>.
 #[post("/strength", data = "<team>")]
-fn reindeer_team_strength(team: Json<Vec<Reindeer<'_>>>) -> String {
-    team.iter()
-        .map(|reindeer| reindeer.strength)
-        .sum::<i32>()
-        .to_string()
+fn reindeer_team_strength(
+    team: Result<TeamBody<Vec<Reindeer<'_>>>, TeamRejection>,
+) -> Result<String, TeamRejection> {
+    let TeamBody(team) = team?;
+    Ok(team_strength(&team).to_string())
+}
+
+#[derive(Debug, Deserialize)]
+struct PartitionRequest<'r> {
+    #[serde(borrow)]
+    pool: Vec<Reindeer<'r>>,
+    teams: usize,
+    #[serde(default = "PartitionRequest::default_field")]
+    field: ReindeerField,
+}
+
+impl<'r> PartitionRequest<'r> {
+    fn default_field() -> ReindeerField {
+        ReindeerField::Strength
+    }
+}
+
+impl<'r> TeamPayload<'r> for PartitionRequest<'r> {
+    const TEAM_KEY: Option<&'static str> = Some("pool");
+}
+
+#[derive(Debug, Serialize)]
+struct PartitionTeam<'r> {
+    members: Vec<Reindeer<'r>>,
+    total: f64,
+}
+
+#[derive(Debug, Serialize)]
+struct Partition<'r> {
+    teams: Vec<PartitionTeam<'r>>,
+    /// Difference between the largest and smallest team totals.
+    gap: f64,
+}
+
+/// Upper bound on refinement passes after the greedy assignment.
+const MAX_PARTITION_PASSES: usize = 1000;
+
+/// Largest pool accepted, since every refinement pass compares each pair of reindeer.
+const MAX_PARTITION_POOL: usize = 500;
+
+/// A team and its total, ordered so that a [`BinaryHeap`] pops the smallest total first.
+///
+/// Ties go to the lowest team index, like `min_by` over the totals would.
+#[derive(Debug, PartialEq)]
+struct SmallestTeam(f64, usize);
+
+impl Eq for SmallestTeam {}
+
+impl PartialOrd for SmallestTeam {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SmallestTeam {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (other.0.total_cmp(&self.0)).then(other.1.cmp(&self.1))
+    }
+}
+
+/// Assign each value to a team, keeping team totals as even as possible.
+///
+/// Values are handed out largest first to the team with the smallest total, then reindeer are
+/// moved or swapped between the largest and smallest teams while that narrows their gap.
+fn balance_teams(values: &[f64], team_count: usize) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..values.len()).collect();
+    let mut assignment = vec![0; values.len()];
+    let mut totals = vec![0.0; team_count];
+    let extreme_teams = |totals: &[f64]| {
+        let by_total = |a: &(usize, &f64), b: &(usize, &f64)| a.1.total_cmp(b.1);
+        let (max, _) = totals.iter().enumerate().max_by(by_total).unwrap();
+        let (min, _) = totals.iter().enumerate().min_by(by_total).unwrap();
+        (max, min)
+    };
+
+    order.sort_by(|&a, &b| values[b].total_cmp(&values[a]));
+
+    let mut smallest: BinaryHeap<_> = (0..team_count)
+        .map(|team| SmallestTeam(0.0, team))
+        .collect();
+
+    for i in order {
+        let SmallestTeam(total, team) = smallest.pop().unwrap();
+        assignment[i] = team;
+        totals[team] = total + values[i];
+        smallest.push(SmallestTeam(totals[team], team));
+    }
+
+    for _ in 0..MAX_PARTITION_PASSES {
+        let (max, min) = extreme_teams(&totals);
+        let gap = totals[max] - totals[min];
+        let members = |team| -> Vec<usize> {
+            (0..values.len())
+                .filter(|&i| assignment[i] == team)
+                .collect()
+        };
+        let (max_members, min_members) = (members(max), members(min));
+        // Moving `delta` from the max team to the min team leaves them `|gap - 2 delta|` apart.
+        let moves = max_members.iter().map(|&i| (i, None, values[i]));
+        let swaps = max_members.iter().flat_map(|&i| {
+            min_members
+                .iter()
+                .map(move |&j| (i, Some(j), values[i] - values[j]))
+        });
+        let best = moves
+            .chain(swaps)
+            .map(|(i, j, delta)| (i, j, delta, (gap - 2.0 * delta).abs()))
+            .filter(|&(_, _, _, narrowed)| narrowed < gap)
+            .min_by(|a, b| a.3.total_cmp(&b.3));
+
+        let Some((i, j, delta, _)) = best else {
+            break;
+        };
+
+        assignment[i] = min;
+
+        if let Some(j) = j {
+            assignment[j] = max;
+        }
+
+        totals[max] -= delta;
+        totals[min] += delta;
+    }
+
+    assignment
+}
+
+/// Split the pool into `teams` teams with totals of `field` as equal as possible.
+#[post("/partition", data = "<request>")]
+fn reindeer_partition<'r>(
+    request: Result<TeamBody<PartitionRequest<'r>>, TeamRejection>,
+) -> Result<Json<Partition<'r>>, TeamError> {
+    let TeamBody(PartitionRequest { pool, teams, field }) = request?;
+
+    if teams == 0 {
+        return Err((Status::BadRequest, "At least one team is needed".to_owned()).into());
+    }
+
+    if pool.len() > MAX_PARTITION_POOL {
+        let message = format!("Too many reindeer, the limit is {MAX_PARTITION_POOL}");
+        return Err((Status::BadRequest, message).into());
+    }
+
+    if teams > pool.len() {
+        let message = format!("Cannot make {teams} teams out of {} reindeer", pool.len());
+        return Err((Status::BadRequest, message).into());
+    }
+
+    if !field.is_numeric() {
+        let message = format!("`{}` is not a numeric field", field.key());
+        return Err((Status::BadRequest, message).into());
+    }
+
+    let values: Vec<f64> = pool
+        .iter()
+        .map(|reindeer| reindeer.field(field).as_f64().unwrap_or_default())
+        .collect();
+    let assignment = balance_teams(&values, teams);
+    let mut teams: Vec<PartitionTeam<'r>> = (0..teams)
+        .map(|_| PartitionTeam {
+            members: Vec::new(),
+            total: 0.0,
+        })
+        .collect();
+
+    for ((reindeer, value), team) in pool.into_iter().zip(values).zip(assignment) {
+        teams[team].members.push(reindeer);
+        teams[team].total += value;
+    }
+
+    let totals = teams.iter().map(|team| team.total);
+    let gap = totals.clone().fold(f64::MIN, f64::max) - totals.fold(f64::MAX, f64::min);
+
+    Ok(Json(Partition { teams, gap }))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(try_from = "String")]
+enum ReindeerField {
+    Name,
+    Strength,
+    Speed,
+    Height,
+    AntlerWidth,
+    SnowMagicPower,
+    FavoriteFood,
+    CandiesEatenYesterday,
+}
+
+impl ReindeerField {
+    const ALL: [ReindeerField; 8] = [
+        ReindeerField::Name,
+        ReindeerField::Strength,
+        ReindeerField::Speed,
+        ReindeerField::Height,
+        ReindeerField::AntlerWidth,
+        ReindeerField::SnowMagicPower,
+        ReindeerField::FavoriteFood,
+        ReindeerField::CandiesEatenYesterday,
+    ];
+
+    /// The JSON key of this field on a [`Reindeer`].
+    fn key(self) -> &'static str {
+        match self {
+            ReindeerField::Name => "name",
+            ReindeerField::Strength => "strength",
+            ReindeerField::Speed => "speed",
+            ReindeerField::Height => "height",
+            ReindeerField::AntlerWidth => "antler_width",
+            ReindeerField::SnowMagicPower => "snow_magic_power",
+            ReindeerField::FavoriteFood => "favorite_food",
+            ReindeerField::CandiesEatenYesterday => "cAnD13s_3ATeN-yesT3rdAy",
+        }
+    }
+
+    fn is_numeric(self) -> bool {
+        !matches!(self, ReindeerField::Name | ReindeerField::FavoriteFood)
+    }
+}
+
+impl FromStr for ReindeerField {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ReindeerField::ALL
+            .into_iter()
+            .find(|field| field.key() == s)
+            .ok_or_else(|| format!("Unknown reindeer field `{s}`"))
+    }
+}
+
+impl TryFrom<String> for ReindeerField {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FieldValue<'r> {
+    Integer(i32),
+    Float(f64),
+    Text(&'r str),
+}
+
+impl<'r> FieldValue<'r> {
+    fn compare(&self, other: &FieldValue<'r>) -> Ordering {
+        match (self, other) {
+            (FieldValue::Integer(a), FieldValue::Integer(b)) => a.cmp(b),
+            (FieldValue::Float(a), FieldValue::Float(b)) => a.total_cmp(b),
+            (FieldValue::Text(a), FieldValue::Text(b)) => a.cmp(b),
+            _ => Ordering::Equal,
+        }
+    }
+}
+
+impl<'r> FieldValue<'r> {
+    fn as_f64(&self) -> Option<f64> {
+        match self {
+            FieldValue::Integer(value) => Some(*value as f64),
+            FieldValue::Float(value) => Some(*value),
+            FieldValue::Text(_) => None,
+        }
+    }
+}
+
+impl<'r> Display for FieldValue<'r> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldValue::Integer(value) => write!(f, "{value}"),
+            FieldValue::Float(value) => write!(f, "{value}"),
+            FieldValue::Text(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+impl<'r> Reindeer<'r> {
+    fn field(&self, field: ReindeerField) -> FieldValue<'r> {
+        match field {
+            ReindeerField::Name => FieldValue::Text(self.name),
+            ReindeerField::Strength => FieldValue::Integer(self.strength),
+            ReindeerField::Speed => FieldValue::Float(self.speed),
+            ReindeerField::Height => FieldValue::Integer(self.height),
+            ReindeerField::AntlerWidth => FieldValue::Integer(self.antler_width),
+            ReindeerField::SnowMagicPower => FieldValue::Integer(self.snow_magic_power),
+            ReindeerField::FavoriteFood => FieldValue::Text(self.favorite_food),
+            ReindeerField::CandiesEatenYesterday => {
+                FieldValue::Integer(self.candies_eaten_yesterday)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct FieldError {
+    /// Position of the reindeer in the team, if the error is about a single reindeer.
+    index: Option<usize>,
+    field: Option<String>,
+    message: String,
+}
+
+impl FieldError {
+    fn new(index: Option<usize>, field: Option<&str>, message: &str) -> Self {
+        FieldError {
+            index,
+            field: field.map(str::to_owned),
+            message: message.to_owned(),
+        }
+    }
+}
+
+/// Check every reindeer of a team for unknown, missing and out-of-range fields.
+fn validate_team(team: &Value) -> Vec<FieldError> {
+    let Value::Array(team) = team else {
+        return vec![FieldError::new(None, None, "Expected an array of reindeer")];
+    };
+    let mut errors = Vec::new();
+
+    for (index, reindeer) in team.iter().enumerate() {
+        let Value::Object(reindeer) = reindeer else {
+            errors.push(FieldError::new(Some(index), None, "Expected an object"));
+            continue;
+        };
+
+        for key in reindeer.keys() {
+            if key.parse::<ReindeerField>().is_err() {
+                errors.push(FieldError::new(Some(index), Some(key), "Unknown field"));
+            }
+        }
+
+        for field in ReindeerField::ALL {
+            let key = field.key();
+            let message = match (field, reindeer.get(key)) {
+                (_, None) => "Missing field",
+                (ReindeerField::Name | ReindeerField::FavoriteFood, Some(Value::String(_))) => {
+                    continue
+                }
+                (ReindeerField::Name | ReindeerField::FavoriteFood, Some(_)) => "Expected a string",
+                (ReindeerField::Speed, Some(Value::Number(number))) => match number.as_f64() {
+                    Some(speed) if speed.is_nan() => "Must be a number",
+                    Some(speed) if speed < 0.0 => "Must not be negative",
+                    _ => continue,
+                },
+                (_, Some(Value::Number(number))) => match number.as_i64() {
+                    Some(value) if value < 0 => "Must not be negative",
+                    Some(value) if value > i32::MAX as i64 => "Too large",
+                    Some(_) => continue,
+                    None => "Expected an integer",
+                },
+                (_, Some(_)) => "Expected a number",
+            };
+
+            errors.push(FieldError::new(Some(index), Some(key), message));
+        }
+    }
+
+    errors
+}
+
+/// Rejected team payload, responding with the list of errors as JSON.
+#[derive(Debug, Clone, PartialEq)]
+struct TeamRejection(Status, Vec<FieldError>);
+
+impl<'r> Responder<'r, 'static> for TeamRejection {
+    fn respond_to(self, request: &'r Request<'_>) -> rocket::response::Result<'static> {
+        let TeamRejection(status, errors) = self;
+
+        Response::build_from(Json(errors).respond_to(request)?)
+            .status(status)
+            .ok()
+    }
+}
+
+/// A request body that carries a team of reindeer.
+trait TeamPayload<'r>: Deserialize<'r> {
+    /// Key of the team in the body, or `None` when the body is the team itself.
+    const TEAM_KEY: Option<&'static str>;
+}
+
+impl<'r> TeamPayload<'r> for Vec<Reindeer<'r>> {
+    const TEAM_KEY: Option<&'static str> = None;
 }
 
-trait ContestAward {
-    fn award_text(&self) -> String;
+impl<'r> TeamPayload<'r> for Vec<Map<String, Value>> {
+    const TEAM_KEY: Option<&'static str> = None;
 }
 
-struct FastestReindeer<'r>(&'r Reindeer<'r>);
+/// A team of reindeer from the request body, or a payload carrying one.
+///
+/// Strict validation of the team is opted into with the `strict` query parameter, or with the
+/// header `Reindeer-Validation: strict`. Otherwise missing fields default to zero, as usual.
+#[derive(Debug)]
+struct TeamBody<T>(T);
+
+impl<'r, T: TeamPayload<'r>> TeamBody<T> {
+    fn is_strict(request: &Request<'_>) -> bool {
+        let strict_query = request
+            .query_value::<bool>("strict")
+            .and_then(Result::ok)
+            .unwrap_or_default();
+        let strict_header = request
+            .headers()
+            .get_one("Reindeer-Validation")
+            .is_some_and(|mode| mode.eq_ignore_ascii_case("strict"));
+
+        strict_query || strict_header
+    }
+
+    fn parse(body: &'r str, strict: bool) -> Result<Self, TeamRejection> {
+        let json_err = |err: serde_json::Error| {
+            let status = if err.is_data() {
+                Status::UnprocessableEntity
+            } else {
+                Status::BadRequest
+            };
+            TeamRejection(status, vec![FieldError::new(None, None, &err.to_string())])
+        };
+
+        if strict {
+            let payload: Value = serde_json::from_str(body).map_err(json_err)?;
+            let team = match T::TEAM_KEY {
+                None => Some(&payload),
+                Some(key) => payload.get(key),
+            };
+            // A payload without a team is left for deserialization to reject.
+            let errors = team.map(validate_team).unwrap_or_default();
 
-impl<'r> ContestAward for FastestReindeer<'r> {
-    fn award_text(&self) -> String {
-        let strength = self.0.strength;
-        let name = self.0.name;
-        format!("Speeding past the finish line with a strength of {strength} is {name}")
+            if !errors.is_empty() {
+                return Err(TeamRejection(Status::UnprocessableEntity, errors));
+            }
+        }
+
+        serde_json::from_str(body).map(TeamBody).map_err(json_err)
     }
 }
 
-struct TallestReindeer<'r>(&'r Reindeer<'r>);
+#[rocket::async_trait]
+impl<'r, T: TeamPayload<'r> + Send> FromData<'r> for TeamBody<T> {
+    type Error = TeamRejection;
 
-impl<'r> ContestAward for TallestReindeer<'r> {
-    fn award_text(&self) -> String {
-        let name = self.0.name;
-        let antler_width = self.0.antler_width;
-        format!("{name} is standing tall with his {antler_width} cm wide antlers")
+    async fn from_data(request: &'r Request<'_>, data: Data<'r>) -> data::Outcome<'r, Self> {
+        let reject = |status: Status, message: &str| {
+            let error = FieldError::new(None, None, message);
+            data::Outcome::Error((status, TeamRejection(status, vec![error])))
+        };
+        // The body is JSON, so it gets the same limit as `Json` rather than the `string` one.
+        let limit = request.limits().get("json").unwrap_or(Limits::JSON);
+        let body = match data.open(limit).into_string().await {
+            Ok(body) if body.is_complete() => body.into_inner(),
+            Ok(_) => return reject(Status::PayloadTooLarge, "Data limit exceeded"),
+            Err(err) => return reject(Status::BadRequest, &err.to_string()),
+        };
+        let body: &'r str = local_cache!(request, body);
+
+        match TeamBody::parse(body, TeamBody::<T>::is_strict(request)) {
+            Ok(payload) => data::Outcome::Success(payload),
+            Err(rejection) => data::Outcome::Error((rejection.0, rejection)),
+        }
     }
 }
 
-struct MagicianReindeer<'r>(&'r Reindeer<'r>);
+/// A failed request about a team: either its payload was rejected, or handling it failed.
+#[derive(Debug, Responder)]
+enum TeamError {
+    Rejected(TeamRejection),
+    Failed((Status, String)),
+}
+
+impl From<TeamRejection> for TeamError {
+    fn from(value: TeamRejection) -> Self {
+        TeamError::Rejected(value)
+    }
+}
 
-impl<'r> ContestAward for MagicianReindeer<'r> {
-    fn award_text(&self) -> String {
-        let name = self.0.name;
-        let snow_magic_power = self.0.snow_magic_power;
-        format!("{name} could blast you away with a snow magic power of {snow_magic_power}")
+impl From<(Status, String)> for TeamError {
+    fn from(value: (Status, String)) -> Self {
+        TeamError::Failed(value)
     }
 }
 
-struct ConsumerReindeer<'r>(&'r Reindeer<'r>);
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum AwardRank {
+    #[default]
+    Max,
+    Min,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct AwardDefinition {
+    field: ReindeerField,
+    #[serde(default)]
+    rank: AwardRank,
+    /// Award text, where `{field}` placeholders are replaced by the winner's values.
+    template: String,
+}
+
+impl AwardDefinition {
+    fn new(field: ReindeerField, template: &str) -> Self {
+        AwardDefinition {
+            field,
+            rank: AwardRank::Max,
+            template: template.to_owned(),
+        }
+    }
+
+    fn winner<'a, 'r>(&self, team: &'a [Reindeer<'r>]) -> Option<&'a Reindeer<'r>> {
+        let by_field = |r1: &&Reindeer<'r>, r2: &&Reindeer<'r>| {
+            r1.field(self.field).compare(&r2.field(self.field))
+        };
 
-impl<'r> ContestAward for ConsumerReindeer<'r> {
-    fn award_text(&self) -> String {
-        let name = self.0.name;
-        let favorite_food = self.0.favorite_food;
-        format!("{name} ate lots of candies, but also some {favorite_food}")
+        match self.rank {
+            AwardRank::Max => team.iter().max_by(by_field),
+            AwardRank::Min => team.iter().min_by(by_field),
+        }
+    }
+
+    fn award_text(&self, reindeer: &Reindeer<'_>) -> Result<String, String> {
+        let mut text = String::with_capacity(self.template.len());
+        let mut rest = self.template.as_str();
+
+        while let Some(index) = rest.find(['{', '}']) {
+            text.push_str(&rest[..index]);
+            rest = &rest[index..];
+
+            if rest.starts_with("{{") || rest.starts_with("}}") {
+                text.push_str(&rest[..1]);
+                rest = &rest[2..];
+            } else if let Some((placeholder, after)) = rest[1..].split_once('}') {
+                let field: ReindeerField = placeholder.parse()?;
+                text.push_str(&reindeer.field(field).to_string());
+                rest = after;
+            } else {
+                return Err(format!("Unmatched brace in template `{}`", self.template));
+            }
+        }
+
+        text.push_str(rest);
+
+        Ok(text)
+    }
+}
+
+/// Award names mapped to their definitions, in response order.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(transparent)]
+struct ContestDefinition(IndexMap<String, AwardDefinition>);
+
+impl Default for ContestDefinition {
+    fn default() -> Self {
+        ContestDefinition(IndexMap::from([
+            (
+                "fastest".to_owned(),
+                AwardDefinition::new(
+                    ReindeerField::Speed,
+                    "Speeding past the finish line with a strength of {strength} is {name}",
+                ),
+            ),
+            (
+                "tallest".to_owned(),
+                AwardDefinition::new(
+                    ReindeerField::Height,
+                    "{name} is standing tall with his {antler_width} cm wide antlers",
+                ),
+            ),
+            (
+                "magician".to_owned(),
+                AwardDefinition::new(
+                    ReindeerField::SnowMagicPower,
+                    "{name} could blast you away with a snow magic power of {snow_magic_power}",
+                ),
+            ),
+            (
+                "consumer".to_owned(),
+                AwardDefinition::new(
+                    ReindeerField::CandiesEatenYesterday,
+                    "{name} ate lots of candies, but also some {favorite_food}",
+                ),
+            ),
+        ]))
+    }
+}
+
+impl ContestDefinition {
+    /// The award text for each award, or an empty text when the team is empty.
+    fn award_texts(&self, team: &[Reindeer<'_>]) -> Result<IndexMap<String, String>, String> {
+        let blank = Reindeer::default();
+
+        self.0
+            .iter()
+            .map(|(award, definition)| {
+                // Render a blank reindeer first so bad templates fail even for an empty team.
+                definition
+                    .award_text(&blank)
+                    .map_err(|err| format!("Award `{award}`: {err}"))?;
+
+                let text = match definition.winner(team) {
+                    Some(reindeer) => definition.award_text(reindeer)?,
+                    None => String::new(),
+                };
+
+                Ok((award.to_owned(), text))
+            })
+            .collect()
     }
 }
 
@@ -76,38 +656,760 @@ struct ReindeerContest {
     consumer: String,
 }
 
+impl ReindeerContest {
+    fn from_team(team: &[Reindeer<'_>]) -> Self {
+        let mut awards = ContestDefinition::default()
+            .award_texts(team)
+            .expect("Default contest templates are valid");
+        let mut take_award = |award: &str| awards.swap_remove(award).unwrap_or_default();
+
+        ReindeerContest {
+            fastest: take_award("fastest"),
+            tallest: take_award("tallest"),
+            magician: take_award("magician"),
+            consumer: take_award("consumer"),
+        }
+    }
+}
+
 #[post("/contest", data = "<team>")]
-fn reindeer_contest(team: Json<Vec<Reindeer<'_>>>) -> Json<ReindeerContest> {
-    let fastest = team
-        .iter()
-        .max_by(|&r1, &r2| r1.speed.total_cmp(&r2.speed))
-        .map(|r| FastestReindeer(r).award_text())
-        .unwrap_or_default();
-    let tallest = team
-        .iter()
-        .max_by_key(|&r| r.height)
-        .map(|r| TallestReindeer(r).award_text())
-        .unwrap_or_default();
-    let magician = team
+fn reindeer_contest(
+    team: Result<TeamBody<Vec<Reindeer<'_>>>, TeamRejection>,
+) -> Result<Json<ReindeerContest>, TeamRejection> {
+    let TeamBody(team) = team?;
+    Ok(Json(ReindeerContest::from_team(&team)))
+}
+
+#[derive(Debug, Deserialize)]
+struct CustomContest<'r> {
+    #[serde(borrow)]
+    team: Vec<Reindeer<'r>>,
+    #[serde(default)]
+    awards: ContestDefinition,
+}
+
+impl<'r> TeamPayload<'r> for CustomContest<'r> {
+    const TEAM_KEY: Option<&'static str> = Some("team");
+}
+
+/// Like `/contest`, but with the awards given along with the team.
+#[post("/contest/custom", data = "<contest>")]
+fn reindeer_custom_contest(
+    contest: Result<TeamBody<CustomContest<'_>>, TeamRejection>,
+) -> Result<Json<IndexMap<String, String>>, TeamError> {
+    let TeamBody(contest) = contest?;
+
+    contest
+        .awards
+        .award_texts(&contest.team)
+        .map(Json)
+        .map_err(|err| (Status::BadRequest, err).into())
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, FromFormField)]
+enum MissingValues {
+    /// Leave reindeer without a value unranked, and out of the stats.
+    #[default]
+    Skip,
+    /// Count a missing value as zero, like a [`Reindeer`] would.
+    Zero,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct RankedReindeer {
+    /// Dense rank, where tied values share a rank. Unranked when the value is missing or NaN.
+    rank: Option<usize>,
+    name: String,
+    value: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct MetricStats {
+    count: usize,
+    min: f64,
+    max: f64,
+    mean: f64,
+    median: f64,
+    stddev: f64,
+}
+
+impl MetricStats {
+    /// Summarize values which are already sorted in descending order.
+    fn from_sorted(values: &[f64]) -> Option<MetricStats> {
+        let count = values.len();
+        let max = *values.first()?;
+        let min = *values.last()?;
+        let mean = values.iter().sum::<f64>() / count as f64;
+        let median = if count % 2 == 0 {
+            (values[count / 2 - 1] + values[count / 2]) / 2.0
+        } else {
+            values[count / 2]
+        };
+        let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / count as f64;
+
+        Some(MetricStats {
+            count,
+            min,
+            max,
+            mean,
+            median,
+            stddev: variance.sqrt(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct MetricLeaderboard {
+    ranking: Vec<RankedReindeer>,
+    winners: Vec<String>,
+    stats: Option<MetricStats>,
+}
+
+impl MetricLeaderboard {
+    /// Rank reindeer by value, highest first. Ties keep their input order.
+    fn new(entries: Vec<(String, Option<f64>)>) -> Self {
+        let (mut ranked, unranked): (Vec<_>, Vec<_>) = entries
+            .into_iter()
+            .partition(|(_, value)| value.is_some_and(|value| !value.is_nan()));
+
+        ranked.sort_by(|(_, a), (_, b)| b.unwrap_or_default().total_cmp(&a.unwrap_or_default()));
+
+        let values: Vec<f64> = ranked.iter().flat_map(|(_, value)| *value).collect();
+        let mut rank = 0;
+        let mut previous = None;
+        let mut ranking = Vec::with_capacity(ranked.len() + unranked.len());
+
+        for (name, value) in ranked {
+            if previous != value {
+                rank += 1;
+                previous = value;
+            }
+
+            ranking.push(RankedReindeer {
+                rank: Some(rank),
+                name,
+                value,
+            });
+        }
+
+        ranking.extend(unranked.into_iter().map(|(name, value)| RankedReindeer {
+            rank: None,
+            name,
+            value: value.filter(|value| !value.is_nan()),
+        }));
+
+        let winners = ranking
+            .iter()
+            .filter(|ranked| ranked.rank == Some(1))
+            .map(|ranked| ranked.name.clone())
+            .collect();
+
+        MetricLeaderboard {
+            ranking,
+            winners,
+            stats: MetricStats::from_sorted(&values),
+        }
+    }
+}
+
+type Leaderboard = IndexMap<&'static str, MetricLeaderboard>;
+
+/// Rank the team by every numeric field.
+///
+/// Reindeer are read as plain JSON objects, so an absent or `null` field can be told apart from
+/// a zero. How those are ranked is chosen by `missing`, unless strict validation rejects them.
+#[post("/leaderboard?<missing>", data = "<team>")]
+fn reindeer_leaderboard(
+    team: Result<TeamBody<Vec<Map<String, Value>>>, TeamRejection>,
+    missing: Option<MissingValues>,
+) -> Result<Json<Leaderboard>, TeamError> {
+    let TeamBody(team) = team?;
+    let missing = missing.unwrap_or_default();
+    let names: Vec<String> = team
         .iter()
-        .max_by_key(|&r| r.snow_magic_power)
-        .map(|r| MagicianReindeer(r).award_text())
-        .unwrap_or_default();
-    let consumer = team
+        .map(|reindeer| match reindeer.get(ReindeerField::Name.key()) {
+            Some(Value::String(name)) => name.to_owned(),
+            _ => String::new(),
+        })
+        .collect();
+    let mut leaderboard = Leaderboard::new();
+
+    for field in ReindeerField::ALL.into_iter().filter(|f| f.is_numeric()) {
+        let entries = team
+            .iter()
+            .zip(&names)
+            .enumerate()
+            .map(|(index, (reindeer, name))| {
+                let value = match reindeer.get(field.key()) {
+                    Some(Value::Number(number)) => number.as_f64(),
+                    Some(Value::Null) | None if missing == MissingValues::Zero => Some(0.0),
+                    Some(Value::Null) | None => None,
+                    Some(_) => {
+                        let key = field.key();
+                        let message = format!("Reindeer {index}: `{key}` is not a number");
+                        return Err((Status::BadRequest, message));
+                    }
+                };
+
+                Ok((name.to_owned(), value))
+            })
+            .collect::<Result<_, _>>()?;
+
+        leaderboard.insert(field.key(), MetricLeaderboard::new(entries));
+    }
+
+    Ok(Json(leaderboard))
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+struct TeamConstraint {
+    field: ReindeerField,
+    limit: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct OptimizeRequest<'r> {
+    #[serde(borrow)]
+    pool: Vec<Reindeer<'r>>,
+    /// Field whose team total is maximized.
+    target: ReindeerField,
+    /// The team total of this field must not exceed the limit.
+    constraint: TeamConstraint,
+    size: usize,
+}
+
+impl<'r> TeamPayload<'r> for OptimizeRequest<'r> {
+    const TEAM_KEY: Option<&'static str> = Some("pool");
+}
+
+#[derive(Debug, Serialize)]
+struct OptimizedTeam<'r> {
+    team: Vec<Reindeer<'r>>,
+    target_total: f64,
+    constraint_total: f64,
+    /// Whether the team is proven optimal, rather than found by the heuristic.
+    exact: bool,
+}
+
+/// Pools up to this size are searched exhaustively.
+const EXACT_POOL_LIMIT: usize = 20;
+
+/// Largest pool accepted, since every heuristic swap compares each pair of reindeer.
+const MAX_OPTIMIZE_POOL: usize = 500;
+
+/// Upper bound on swaps made by the heuristic, after which its team is returned as is.
+const MAX_OPTIMIZE_SWAPS: usize = 1000;
+
+/// A candidate reindeer, with its target and constraint values.
+#[derive(Debug, Clone, Copy)]
+struct Candidate {
+    index: usize,
+    target: f64,
+    cost: f64,
+}
+
+/// Branch and bound over candidates sorted by target, highest first.
+fn optimize_exact(candidates: &[Candidate], size: usize, limit: f64) -> Option<Vec<usize>> {
+    struct Search<'a> {
+        candidates: &'a [Candidate],
+        size: usize,
+        limit: f64,
+        min_cost_from: Vec<f64>,
+        chosen: Vec<usize>,
+        best: Option<(f64, Vec<usize>)>,
+    }
+
+    impl<'a> Search<'a> {
+        fn visit(&mut self, start: usize, target: f64, cost: f64) {
+            let need = self.size - self.chosen.len();
+
+            if need == 0 {
+                if self.best.as_ref().map_or(true, |(best, _)| target > *best) {
+                    self.best = Some((target, self.chosen.clone()));
+                }
+                return;
+            }
+
+            for i in start..=self.candidates.len() - need {
+                // The next `need` candidates have the highest remaining targets.
+                let bound: f64 = target
+                    + self.candidates[i..i + need]
+                        .iter()
+                        .map(|c| c.target)
+                        .sum::<f64>();
+                let lowest_cost = cost + self.min_cost_from[i] * need as f64;
+
+                if self.best.as_ref().is_some_and(|(best, _)| bound <= *best) {
+                    return;
+                }
+
+                if lowest_cost > self.limit {
+                    continue;
+                }
+
+                let candidate = self.candidates[i];
+
+                if cost + candidate.cost <= self.limit {
+                    self.chosen.push(candidate.index);
+                    self.visit(i + 1, target + candidate.target, cost + candidate.cost);
+                    self.chosen.pop();
+                }
+            }
+        }
+    }
+
+    let mut min_cost_from = vec![f64::INFINITY; candidates.len() + 1];
+
+    for (i, candidate) in candidates.iter().enumerate().rev() {
+        min_cost_from[i] = min_cost_from[i + 1].min(candidate.cost);
+    }
+
+    let mut search = Search {
+        candidates,
+        size,
+        limit,
+        min_cost_from,
+        chosen: Vec::with_capacity(size),
+        best: None,
+    };
+
+    search.visit(0, 0.0, 0.0);
+    search.best.map(|(_, chosen)| chosen)
+}
+
+/// Start from the cheapest team, then swap in better reindeer while the team stays in budget.
+///
+/// Stops after [`MAX_OPTIMIZE_SWAPS`] swaps, keeping the best team found so far.
+fn optimize_heuristic(candidates: &[Candidate], size: usize, limit: f64) -> Option<Vec<usize>> {
+    let mut by_cost = candidates.to_vec();
+
+    by_cost.sort_by(|a, b| a.cost.total_cmp(&b.cost));
+
+    let (chosen, rest) = by_cost.split_at(size);
+    let mut chosen = chosen.to_vec();
+    let mut rest = rest.to_vec();
+    let mut cost: f64 = chosen.iter().map(|c| c.cost).sum();
+
+    if cost > limit {
+        return None;
+    }
+
+    for _ in 0..MAX_OPTIMIZE_SWAPS {
+        let best_swap = chosen
+            .iter()
+            .enumerate()
+            .flat_map(|(i, a)| rest.iter().enumerate().map(move |(j, b)| (i, j, a, b)))
+            .filter(|(_, _, a, b)| b.target > a.target && cost - a.cost + b.cost <= limit)
+            .max_by(|(_, _, a1, b1), (_, _, a2, b2)| {
+                (b1.target - a1.target).total_cmp(&(b2.target - a2.target))
+            })
+            .map(|(i, j, _, _)| (i, j));
+
+        let Some((i, j)) = best_swap else {
+            break;
+        };
+
+        cost += rest[j].cost - chosen[i].cost;
+        std::mem::swap(&mut chosen[i], &mut rest[j]);
+    }
+
+    Some(chosen.iter().map(|c| c.index).collect())
+}
+
+/// Pick the `size` reindeer with the highest total `target`, within the `constraint` limit.
+#[post("/optimize", data = "<request>")]
+fn reindeer_optimize<'r>(
+    request: Result<TeamBody<OptimizeRequest<'r>>, TeamRejection>,
+) -> Result<Json<OptimizedTeam<'r>>, TeamError> {
+    let TeamBody(OptimizeRequest {
+        pool,
+        target,
+        constraint,
+        size,
+    }) = request?;
+
+    for field in [target, constraint.field] {
+        if !field.is_numeric() {
+            let message = format!("`{}` is not a numeric field", field.key());
+            return Err((Status::BadRequest, message).into());
+        }
+    }
+
+    if pool.len() > MAX_OPTIMIZE_POOL {
+        let message = format!("Too many reindeer, the limit is {MAX_OPTIMIZE_POOL}");
+        return Err((Status::BadRequest, message).into());
+    }
+
+    if size > pool.len() {
+        let message = format!("Team size {size} is larger than the pool");
+        return Err((Status::BadRequest, message).into());
+    }
+
+    let value = |reindeer: &Reindeer<'_>, field| reindeer.field(field).as_f64().unwrap_or_default();
+    let mut candidates: Vec<Candidate> = pool
         .iter()
-        .max_by_key(|&r| r.candies_eaten_yesterday)
-        .map(|r| ConsumerReindeer(r).award_text())
-        .unwrap_or_default();
-    Json(ReindeerContest {
-        fastest,
-        tallest,
-        magician,
-        consumer,
-    })
+        .enumerate()
+        .map(|(index, reindeer)| Candidate {
+            index,
+            target: value(reindeer, target),
+            cost: value(reindeer, constraint.field),
+        })
+        .collect();
+
+    candidates.sort_by(|a, b| b.target.total_cmp(&a.target));
+
+    let exact = pool.len() <= EXACT_POOL_LIMIT;
+    let chosen = if exact {
+        optimize_exact(&candidates, size, constraint.limit)
+    } else {
+        optimize_heuristic(&candidates, size, constraint.limit)
+    };
+    let Some(mut chosen) = chosen else {
+        let message = "No team fits the limit".to_owned();
+        return Err((Status::UnprocessableEntity, message).into());
+    };
+
+    chosen.sort_unstable();
+
+    let team: Vec<Reindeer<'r>> = chosen.into_iter().map(|i| pool[i].clone()).collect();
+    let target_total = team.iter().map(|r| value(r, target)).sum();
+    let constraint_total = team.iter().map(|r| value(r, constraint.field)).sum();
+
+    Ok(Json(OptimizedTeam {
+        team,
+        target_total,
+        constraint_total,
+        exact,
+    }))
+}
+
+macro_rules! server_err {
+    ($err:expr) => {
+        (Status::InternalServerError, $err.to_string())
+    };
+}
+
+/// A stored reindeer, owning what a [`Reindeer`] borrows from the request body.
+#[derive(Debug, FromRow)]
+struct ReindeerRow {
+    name: String,
+    strength: i32,
+    speed: f64,
+    height: i32,
+    antler_width: i32,
+    snow_magic_power: i32,
+    favorite_food: String,
+    candies_eaten_yesterday: i32,
+}
+
+impl ReindeerRow {
+    fn as_reindeer(&self) -> Reindeer<'_> {
+        Reindeer {
+            name: &self.name,
+            strength: self.strength,
+            speed: self.speed,
+            height: self.height,
+            antler_width: self.antler_width,
+            snow_magic_power: self.snow_magic_power,
+            favorite_food: &self.favorite_food,
+            candies_eaten_yesterday: self.candies_eaten_yesterday,
+        }
+    }
+}
+
+#[derive(Debug, FromRow, Serialize)]
+struct RosterVersion {
+    id: i32,
+    version: i32,
+    created_at: String,
+}
+
+#[post("/rosters/reset")]
+async fn reset_rosters(gift_db: &State<GiftDatabase>) -> Result<(), (Status, String)> {
+    let mut transaction = (gift_db.pool.begin())
+        .await
+        .map_err(|err| server_err!(err))?;
+
+    for table in ["roster_reindeer", "roster_versions", "rosters"] {
+        let _result = sqlx::query(&format!("DROP TABLE IF EXISTS {table}"))
+            .execute(&mut *transaction)
+            .await
+            .map_err(|err| server_err!(err))?;
+    }
+
+    let _result = sqlx::query("CREATE TABLE rosters (id SERIAL PRIMARY KEY)")
+        .execute(&mut *transaction)
+        .await
+        .map_err(|err| server_err!(err))?;
+    let _result = sqlx::query(
+        r#"CREATE TABLE roster_versions (
+            roster_id INT REFERENCES rosters (id) ON DELETE CASCADE,
+            version INT,
+            created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+            PRIMARY KEY (roster_id, version)
+        )"#,
+    )
+    .execute(&mut *transaction)
+    .await
+    .map_err(|err| server_err!(err))?;
+    let _result = sqlx::query(
+        r#"CREATE TABLE roster_reindeer (
+            roster_id INT,
+            version INT,
+            position INT,
+            name TEXT NOT NULL,
+            strength INT NOT NULL,
+            speed DOUBLE PRECISION NOT NULL,
+            height INT NOT NULL,
+            antler_width INT NOT NULL,
+            snow_magic_power INT NOT NULL,
+            favorite_food TEXT NOT NULL,
+            candies_eaten_yesterday INT NOT NULL,
+            PRIMARY KEY (roster_id, version, position),
+            FOREIGN KEY (roster_id, version)
+                REFERENCES roster_versions (roster_id, version) ON DELETE CASCADE
+        )"#,
+    )
+    .execute(&mut *transaction)
+    .await
+    .map_err(|err| server_err!(err))?;
+
+    transaction.commit().await.map_err(|err| server_err!(err))?;
+
+    Ok(())
+}
+
+/// Store `team` as the next version of roster `id`.
+///
+/// The caller must hold the lock on the `rosters` row, so that concurrent
+/// writers queue up instead of racing for the same version number.
+async fn insert_roster_version(
+    connection: &mut PgConnection,
+    id: i32,
+    team: &[Reindeer<'_>],
+) -> Result<RosterVersion, (Status, String)> {
+    let roster_version: RosterVersion = sqlx::query_as(
+        r#"INSERT INTO roster_versions (roster_id, version)
+        SELECT $1, COALESCE(MAX(version), 0) + 1
+        FROM roster_versions
+        WHERE roster_id = $1
+        RETURNING roster_id AS id, version, created_at::TEXT"#,
+    )
+    .bind(id)
+    .fetch_one(&mut *connection)
+    .await
+    .map_err(|err| server_err!(err))?;
+
+    if !team.is_empty() {
+        let _result = QueryBuilder::new(
+            r#"INSERT INTO roster_reindeer (
+                roster_id, version, position, name, strength, speed, height,
+                antler_width, snow_magic_power, favorite_food, candies_eaten_yesterday
+            ) "#,
+        )
+        .push_values(
+            team.iter().enumerate(),
+            |mut binder, (position, reindeer)| {
+                binder
+                    .push_bind(id)
+                    .push_bind(roster_version.version)
+                    .push_bind(position as i32)
+                    .push_bind(reindeer.name)
+                    .push_bind(reindeer.strength)
+                    .push_bind(reindeer.speed)
+                    .push_bind(reindeer.height)
+                    .push_bind(reindeer.antler_width)
+                    .push_bind(reindeer.snow_magic_power)
+                    .push_bind(reindeer.favorite_food)
+                    .push_bind(reindeer.candies_eaten_yesterday);
+            },
+        )
+        .build()
+        .execute(&mut *connection)
+        .await
+        .map_err(|err| server_err!(err))?;
+    }
+
+    Ok(roster_version)
+}
+
+/// Load version `version` of roster `id`, or its latest version.
+async fn fetch_roster(
+    gift_db: &GiftDatabase,
+    id: i32,
+    version: Option<i32>,
+) -> Result<Vec<ReindeerRow>, (Status, String)> {
+    let roster_version: Option<(i32,)> = sqlx::query_as(
+        r#"SELECT version
+        FROM roster_versions
+        WHERE roster_id = $1 AND ($2::INT IS NULL OR version = $2)
+        ORDER BY version DESC
+        LIMIT 1"#,
+    )
+    .bind(id)
+    .bind(version)
+    .fetch_optional(&gift_db.pool)
+    .await
+    .map_err(|err| server_err!(err))?;
+    let Some((version,)) = roster_version else {
+        return Err((Status::NotFound, format!("Roster {id} was not found")));
+    };
+    let team: Vec<ReindeerRow> = sqlx::query_as(
+        r#"SELECT name, strength, speed, height, antler_width,
+            snow_magic_power, favorite_food, candies_eaten_yesterday
+        FROM roster_reindeer
+        WHERE roster_id = $1 AND version = $2
+        ORDER BY position ASC"#,
+    )
+    .bind(id)
+    .bind(version)
+    .fetch_all(&gift_db.pool)
+    .await
+    .map_err(|err| server_err!(err))?;
+
+    Ok(team)
+}
+
+#[post("/rosters", data = "<team>")]
+async fn create_roster(
+    team: Result<TeamBody<Vec<Reindeer<'_>>>, TeamRejection>,
+    gift_db: &State<GiftDatabase>,
+) -> Result<Json<RosterVersion>, TeamError> {
+    let TeamBody(team) = team?;
+    let mut transaction = (gift_db.pool.begin())
+        .await
+        .map_err(|err| server_err!(err))?;
+    let (id,): (i32,) = sqlx::query_as("INSERT INTO rosters DEFAULT VALUES RETURNING id")
+        .fetch_one(&mut *transaction)
+        .await
+        .map_err(|err| server_err!(err))?;
+    let roster_version = insert_roster_version(&mut transaction, id, &team).await?;
+
+    transaction.commit().await.map_err(|err| server_err!(err))?;
+
+    Ok(Json(roster_version))
+}
+
+#[put("/rosters/<id>", data = "<team>")]
+async fn update_roster(
+    id: i32,
+    team: Result<TeamBody<Vec<Reindeer<'_>>>, TeamRejection>,
+    gift_db: &State<GiftDatabase>,
+) -> Result<Json<RosterVersion>, TeamError> {
+    let TeamBody(team) = team?;
+    let mut transaction = (gift_db.pool.begin())
+        .await
+        .map_err(|err| server_err!(err))?;
+    let roster: Option<(i32,)> = sqlx::query_as("SELECT id FROM rosters WHERE id = $1 FOR UPDATE")
+        .bind(id)
+        .fetch_optional(&mut *transaction)
+        .await
+        .map_err(|err| server_err!(err))?;
+
+    if roster.is_none() {
+        return Err((Status::NotFound, format!("Roster {id} was not found")).into());
+    }
+
+    let roster_version = insert_roster_version(&mut transaction, id, &team).await?;
+
+    transaction.commit().await.map_err(|err| server_err!(err))?;
+
+    Ok(Json(roster_version))
+}
+
+#[get("/rosters/<id>?<version>")]
+async fn read_roster(
+    id: i32,
+    version: Option<i32>,
+    gift_db: &State<GiftDatabase>,
+) -> Result<Json<serde_json::Value>, (Status, String)> {
+    let rows = fetch_roster(gift_db, id, version).await?;
+    let team: Vec<Reindeer<'_>> = rows.iter().map(ReindeerRow::as_reindeer).collect();
+
+    serde_json::to_value(team)
+        .map(Json)
+        .map_err(|err| server_err!(err))
+}
+
+#[get("/rosters/<id>/versions")]
+async fn roster_versions(
+    id: i32,
+    gift_db: &State<GiftDatabase>,
+) -> Result<Json<Vec<RosterVersion>>, (Status, String)> {
+    let versions: Vec<RosterVersion> = sqlx::query_as(
+        r#"SELECT roster_id AS id, version, created_at::TEXT
+        FROM roster_versions
+        WHERE roster_id = $1
+        ORDER BY version ASC"#,
+    )
+    .bind(id)
+    .fetch_all(&gift_db.pool)
+    .await
+    .map_err(|err| server_err!(err))?;
+
+    if versions.is_empty() {
+        Err((Status::NotFound, format!("Roster {id} was not found")))
+    } else {
+        Ok(Json(versions))
+    }
+}
+
+#[delete("/rosters/<id>")]
+async fn delete_roster(id: i32, gift_db: &State<GiftDatabase>) -> Result<(), (Status, String)> {
+    let result = sqlx::query("DELETE FROM rosters WHERE id = $1")
+        .bind(id)
+        .execute(&gift_db.pool)
+        .await
+        .map_err(|err| server_err!(err))?;
+
+    if result.rows_affected() == 0 {
+        Err((Status::NotFound, format!("Roster {id} was not found")))
+    } else {
+        Ok(())
+    }
+}
+
+#[get("/rosters/<id>/strength?<version>")]
+async fn roster_strength(
+    id: i32,
+    version: Option<i32>,
+    gift_db: &State<GiftDatabase>,
+) -> Result<String, (Status, String)> {
+    let rows = fetch_roster(gift_db, id, version).await?;
+    let team: Vec<Reindeer<'_>> = rows.iter().map(ReindeerRow::as_reindeer).collect();
+
+    Ok(team_strength(&team).to_string())
+}
+
+#[get("/rosters/<id>/contest?<version>")]
+async fn roster_contest(
+    id: i32,
+    version: Option<i32>,
+    gift_db: &State<GiftDatabase>,
+) -> Result<Json<ReindeerContest>, (Status, String)> {
+    let rows = fetch_roster(gift_db, id, version).await?;
+    let team: Vec<Reindeer<'_>> = rows.iter().map(ReindeerRow::as_reindeer).collect();
+
+    Ok(Json(ReindeerContest::from_team(&team)))
 }
 
 pub fn routes() -> Vec<rocket::Route> {
-    rocket::routes![reindeer_team_strength, reindeer_contest]
+    rocket::routes![
+        reindeer_team_strength,
+        reindeer_partition,
+        reindeer_contest,
+        reindeer_custom_contest,
+        reindeer_leaderboard,
+        reindeer_optimize,
+        reset_rosters,
+        create_roster,
+        update_roster,
+        read_roster,
+        roster_versions,
+        delete_roster,
+        roster_strength,
+        roster_contest
+    ]
 }
 
 #[cfg(test)]
@@ -128,15 +1430,15 @@ mod tests_day_04 {
     #[case("[]", "0")]
     #[case(r#"[{"name": "Rudolph", "strength": 2}]"#, "2")]
     fn test_reindeer_team_strength(#[case] body: &str, #[case] expected: &str) {
-        let team: Vec<Reindeer<'_>> = serde_json::from_str(body).unwrap();
-        let result = reindeer_team_strength(Json(team));
+        let team = TeamBody::parse(body, false);
+        let result = reindeer_team_strength(team).unwrap();
 
         assert_eq!(result, expected);
     }
 
     #[test]
     fn test_reindeer_contest() {
-        let team: Vec<Reindeer<'_>> = serde_json::from_str(
+        let team = TeamBody::parse(
             r#"[
                 {
                     "name": "Dasher",
@@ -159,9 +1461,9 @@ mod tests_day_04 {
                     "cAnD13s_3ATeN-yesT3rdAy": 5
                 }
             ]"#,
-        )
-        .unwrap();
-        let Json(result) = reindeer_contest(Json(team));
+            true,
+        );
+        let Json(result) = reindeer_contest(team).unwrap();
         let expected = ReindeerContest {
             fastest: "Speeding past the finish line with a strength of 5 is Dasher".to_owned(),
             tallest: "Dasher is standing tall with his 36 cm wide antlers".to_owned(),
@@ -171,4 +1473,557 @@ mod tests_day_04 {
 
         assert_eq!(result, expected);
     }
+
+    #[test]
+    fn test_reindeer_custom_contest() {
+        let contest: CustomContest<'_> = serde_json::from_str(
+            r#"{
+                "team": [
+                    {"name": "Dasher", "speed": 50.4, "height": 80, "favorite_food": "hay"},
+                    {"name": "Dancer", "speed": 48.2, "height": 65, "favorite_food": "grass"}
+                ],
+                "awards": {
+                    "slowest": {
+                        "field": "speed",
+                        "rank": "min",
+                        "template": "{name} takes {{their}} time at {speed}"
+                    },
+                    "shortest": {
+                        "field": "height",
+                        "rank": "min",
+                        "template": "{name} is {height} cm tall and eats {favorite_food}"
+                    },
+                    "last": {
+                        "field": "name",
+                        "template": "{name}"
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+        let Json(result) = reindeer_custom_contest(Ok(TeamBody(contest))).unwrap();
+        let expected = IndexMap::from([
+            (
+                "slowest".to_owned(),
+                "Dancer takes {their} time at 48.2".to_owned(),
+            ),
+            (
+                "shortest".to_owned(),
+                "Dancer is 65 cm tall and eats grass".to_owned(),
+            ),
+            ("last".to_owned(), "Dasher".to_owned()),
+        ]);
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_reindeer_custom_contest_default_awards() {
+        let contest: CustomContest<'_> =
+            serde_json::from_str(r#"{"team": [{"name": "Rudolph", "favorite_food": "carrots"}]}"#)
+                .unwrap();
+        let Json(result) = reindeer_custom_contest(Ok(TeamBody(contest))).unwrap();
+
+        assert_eq!(
+            result.keys().collect::<Vec<_>>(),
+            ["fastest", "tallest", "magician", "consumer"]
+        );
+        assert_eq!(
+            result["consumer"],
+            "Rudolph ate lots of candies, but also some carrots"
+        );
+    }
+
+    #[rstest]
+    #[case(r#"{"field": "speed", "template": "{nose} is red"}"#)]
+    #[case(r#"{"field": "speed", "template": "{name is red"}"#)]
+    fn test_reindeer_custom_contest_bad_template(#[case] award: &str) {
+        let body = format!(r#"{{"team": [], "awards": {{"red": {award}}}}}"#);
+        let contest: CustomContest<'_> = serde_json::from_str(&body).unwrap();
+        let TeamError::Failed((status, _)) =
+            reindeer_custom_contest(Ok(TeamBody(contest))).unwrap_err()
+        else {
+            panic!("expected a failed request");
+        };
+
+        assert_eq!(status, Status::BadRequest);
+    }
+
+    #[test]
+    fn test_reindeer_field_unknown() {
+        let result = serde_json::from_str::<AwardDefinition>(
+            r#"{"field": "strenght", "template": "{name}"}"#,
+        );
+
+        assert!(result.is_err());
+    }
+
+    fn leaderboard_entries(values: &[(&str, Option<f64>)]) -> Vec<(String, Option<f64>)> {
+        values
+            .iter()
+            .map(|&(name, value)| (name.to_owned(), value))
+            .collect()
+    }
+
+    #[test]
+    fn test_metric_leaderboard_ties() {
+        let leaderboard = MetricLeaderboard::new(leaderboard_entries(&[
+            ("Dasher", Some(5.0)),
+            ("Dancer", Some(7.0)),
+            ("Prancer", None),
+            ("Vixen", Some(7.0)),
+            ("Comet", Some(f64::NAN)),
+            ("Cupid", Some(3.0)),
+        ]));
+        let ranking: Vec<_> = leaderboard
+            .ranking
+            .iter()
+            .map(|ranked| (ranked.rank, ranked.name.as_str()))
+            .collect();
+
+        assert_eq!(
+            ranking,
+            [
+                (Some(1), "Dancer"),
+                (Some(1), "Vixen"),
+                (Some(2), "Dasher"),
+                (Some(3), "Cupid"),
+                (None, "Prancer"),
+                (None, "Comet"),
+            ]
+        );
+        assert_eq!(leaderboard.winners, ["Dancer", "Vixen"]);
+        assert_eq!(leaderboard.ranking[5].value, None);
+
+        let stats = leaderboard.stats.unwrap();
+
+        assert_eq!(stats.count, 4);
+        assert_eq!(stats.min, 3.0);
+        assert_eq!(stats.max, 7.0);
+        assert_eq!(stats.mean, 5.5);
+        assert_eq!(stats.median, 6.0);
+        assert!((stats.stddev - 1.6583123951777).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_metric_leaderboard_empty() {
+        let leaderboard = MetricLeaderboard::new(leaderboard_entries(&[("Rudolph", None)]));
+
+        assert!(leaderboard.winners.is_empty());
+        assert_eq!(leaderboard.stats, None);
+    }
+
+    #[rstest]
+    #[case(None, vec![(Some(1), "Dasher"), (None, "Dancer")])]
+    #[case(Some(MissingValues::Zero), vec![(Some(1), "Dasher"), (Some(2), "Dancer")])]
+    fn test_reindeer_leaderboard_missing(
+        #[case] missing: Option<MissingValues>,
+        #[case] expected: Vec<(Option<usize>, &str)>,
+    ) {
+        let team = serde_json::from_str(
+            r#"[{"name": "Dasher", "strength": 5}, {"name": "Dancer", "strength": null}]"#,
+        )
+        .unwrap();
+        let Json(result) = reindeer_leaderboard(Ok(TeamBody(team)), missing).unwrap();
+        let ranking: Vec<_> = result["strength"]
+            .ranking
+            .iter()
+            .map(|ranked| (ranked.rank, ranked.name.as_str()))
+            .collect();
+
+        assert_eq!(result.len(), 6);
+        assert_eq!(ranking, expected);
+    }
+
+    #[test]
+    fn test_reindeer_leaderboard_not_a_number() {
+        let team = serde_json::from_str(r#"[{"name": "Dasher", "speed": "fast"}]"#).unwrap();
+        let TeamError::Failed((status, message)) =
+            reindeer_leaderboard(Ok(TeamBody(team)), None).unwrap_err()
+        else {
+            panic!("expected a failed request");
+        };
+
+        assert_eq!(status, Status::BadRequest);
+        assert_eq!(message, "Reindeer 0: `speed` is not a number");
+    }
+
+    #[test]
+    fn test_reindeer_leaderboard_strict() {
+        let body = r#"[{"name": "Dasher", "strength": -5, "speed": "fast", "nose": "red"}]"#;
+        let rejection = reindeer_leaderboard(TeamBody::parse(body, true), None).unwrap_err();
+        let TeamError::Rejected(TeamRejection(status, errors)) = rejection else {
+            panic!("expected a rejected payload");
+        };
+        let errors: Vec<_> = errors
+            .iter()
+            .map(|err| (err.field.as_deref(), err.message.as_str()))
+            .collect();
+
+        assert_eq!(status, Status::UnprocessableEntity);
+        assert_eq!(
+            errors[..3],
+            [
+                (Some("nose"), "Unknown field"),
+                (Some("strength"), "Must not be negative"),
+                (Some("speed"), "Expected a number"),
+            ]
+        );
+        assert!(reindeer_leaderboard(TeamBody::parse(body, false), None).is_err());
+    }
+
+    #[test]
+    fn test_reindeer_team_strength_strict() {
+        let body = r#"[
+            {"name": "Dasher", "strenght": 5},
+            "Dancer",
+            {
+                "name": "Prancer",
+                "strength": -4,
+                "speed": 12.5,
+                "height": 4.5,
+                "antler_width": "wide",
+                "snow_magic_power": 3000000000,
+                "favorite_food": null,
+                "cAnD13s_3ATeN-yesT3rdAy": 0
+            }
+        ]"#;
+        let rejection = reindeer_team_strength(TeamBody::parse(body, true)).unwrap_err();
+        let errors: Vec<_> = rejection
+            .1
+            .iter()
+            .map(|err| (err.index, err.field.as_deref(), err.message.as_str()))
+            .collect();
+
+        assert_eq!(rejection.0, Status::UnprocessableEntity);
+        assert_eq!(
+            errors,
+            [
+                (Some(0), Some("strenght"), "Unknown field"),
+                (Some(0), Some("strength"), "Missing field"),
+                (Some(0), Some("speed"), "Missing field"),
+                (Some(0), Some("height"), "Missing field"),
+                (Some(0), Some("antler_width"), "Missing field"),
+                (Some(0), Some("snow_magic_power"), "Missing field"),
+                (Some(0), Some("favorite_food"), "Missing field"),
+                (Some(0), Some("cAnD13s_3ATeN-yesT3rdAy"), "Missing field"),
+                (Some(1), None, "Expected an object"),
+                (Some(2), Some("strength"), "Must not be negative"),
+                (Some(2), Some("height"), "Expected an integer"),
+                (Some(2), Some("antler_width"), "Expected a number"),
+                (Some(2), Some("snow_magic_power"), "Too large"),
+                (Some(2), Some("favorite_food"), "Expected a string"),
+            ]
+        );
+    }
+
+    #[rstest]
+    #[case(0, Status::Ok)]
+    #[case(64, Status::PayloadTooLarge)]
+    fn test_reindeer_team_strength_json_limit(#[case] padding: usize, #[case] status: Status) {
+        let figment = rocket::Config::figment()
+            .merge(("limits.string", 16))
+            .merge(("limits.json", 64));
+        let rocket = rocket::custom(figment).mount("/4", rocket::routes![reindeer_team_strength]);
+        let client = rocket::local::blocking::Client::tracked(rocket).unwrap();
+        let body = format!(
+            r#"[{{"name": "Dasher", "strength": 5}}]{}"#,
+            " ".repeat(padding)
+        );
+        let response = client.post("/4/strength").body(body).dispatch();
+
+        assert_eq!(response.status(), status);
+    }
+
+    #[rstest]
+    #[case("[", Status::BadRequest)]
+    #[case(r#"{"name": "Dasher"}"#, Status::UnprocessableEntity)]
+    fn test_reindeer_team_strength_bad_json(#[case] body: &str, #[case] status: Status) {
+        for strict in [false, true] {
+            let rejection = reindeer_team_strength(TeamBody::parse(body, strict)).unwrap_err();
+
+            assert_eq!(rejection.0, status);
+            assert_eq!(rejection.1.len(), 1);
+        }
+    }
+
+    fn optimize_pool(count: usize) -> String {
+        let pool: Vec<String> = (0..count)
+            .map(|i| {
+                let speed = (i * 37 % 23) as f64 + 0.5;
+                let candies = i * 11 % 7 + 1;
+                format!(
+                    r#"{{"name": "R{i}", "speed": {speed}, "cAnD13s_3ATeN-yesT3rdAy": {candies}}}"#
+                )
+            })
+            .collect();
+        pool.join(",")
+    }
+
+    #[rstest]
+    #[case(2, 10.0, &["Dancer", "Vixen"], 16.0)]
+    #[case(2, 6.0, &["Dasher", "Dancer"], 11.0)]
+    #[case(3, 100.0, &["Dasher", "Dancer", "Vixen"], 19.0)]
+    #[case(0, 0.0, &[], 0.0)]
+    fn test_reindeer_optimize(
+        #[case] size: usize,
+        #[case] limit: f64,
+        #[case] names: &[&str],
+        #[case] speed: f64,
+    ) {
+        let body = format!(
+            r#"{{
+                "pool": [
+                    {{"name": "Dasher", "speed": 3, "cAnD13s_3ATeN-yesT3rdAy": 1}},
+                    {{"name": "Dancer", "speed": 8, "cAnD13s_3ATeN-yesT3rdAy": 5}},
+                    {{"name": "Prancer", "speed": 1, "cAnD13s_3ATeN-yesT3rdAy": 1}},
+                    {{"name": "Vixen", "speed": 8, "cAnD13s_3ATeN-yesT3rdAy": 5}}
+                ],
+                "target": "speed",
+                "constraint": {{"field": "cAnD13s_3ATeN-yesT3rdAy", "limit": {limit}}},
+                "size": {size}
+            }}"#
+        );
+        let request = serde_json::from_str(&body).unwrap();
+        let Json(result) = reindeer_optimize(Ok(TeamBody(request))).unwrap();
+        let team: Vec<_> = result.team.iter().map(|r| r.name).collect();
+
+        assert_eq!(team, names);
+        assert_eq!(result.target_total, speed);
+        assert!(result.exact);
+    }
+
+    #[test]
+    fn test_reindeer_optimize_heuristic() {
+        let limit = 20.0;
+        let body = format!(
+            r#"{{
+                "pool": [{}],
+                "target": "speed",
+                "constraint": {{"field": "cAnD13s_3ATeN-yesT3rdAy", "limit": {limit}}},
+                "size": 5
+            }}"#,
+            optimize_pool(200)
+        );
+        let Json(heuristic) =
+            reindeer_optimize(Ok(TeamBody(serde_json::from_str(&body).unwrap()))).unwrap();
+
+        assert!(!heuristic.exact);
+        assert_eq!(heuristic.team.len(), 5);
+        assert!(heuristic.constraint_total <= limit);
+    }
+
+    #[rstest]
+    #[case(5, 20.0)]
+    #[case(3, 6.0)]
+    #[case(8, 40.0)]
+    fn test_optimize_heuristic_matches_exact(#[case] size: usize, #[case] limit: f64) {
+        let mut candidates: Vec<Candidate> = (0..EXACT_POOL_LIMIT)
+            .map(|index| Candidate {
+                index,
+                target: (index * 37 % 23) as f64 + 0.5,
+                cost: (index * 11 % 7 + 1) as f64,
+            })
+            .collect();
+
+        candidates.sort_by(|a, b| b.target.total_cmp(&a.target));
+
+        let totals = |chosen: Vec<usize>| {
+            let chosen = candidates.iter().filter(|c| chosen.contains(&c.index));
+            chosen.fold((0.0, 0.0), |(target, cost), c| {
+                (target + c.target, cost + c.cost)
+            })
+        };
+        let exact = totals(optimize_exact(&candidates, size, limit).unwrap());
+        let heuristic = totals(optimize_heuristic(&candidates, size, limit).unwrap());
+
+        assert!(heuristic.1 <= limit);
+        assert_eq!(heuristic.0, exact.0);
+    }
+
+    #[rstest]
+    #[case(r#""name""#, 1, 10.0, Status::BadRequest)]
+    #[case(r#""speed""#, 3, 10.0, Status::BadRequest)]
+    #[case(r#""speed""#, 2, 1.0, Status::UnprocessableEntity)]
+    fn test_reindeer_optimize_error(
+        #[case] target: &str,
+        #[case] size: usize,
+        #[case] limit: f64,
+        #[case] status: Status,
+    ) {
+        let body = format!(
+            r#"{{
+                "pool": [{{"name": "Dasher", "strength": 1}}, {{"name": "Dancer", "strength": 1}}],
+                "target": {target},
+                "constraint": {{"field": "strength", "limit": {limit}}},
+                "size": {size}
+            }}"#
+        );
+        let request = serde_json::from_str(&body).unwrap();
+        let TeamError::Failed((result, _)) = reindeer_optimize(Ok(TeamBody(request))).unwrap_err()
+        else {
+            panic!("expected a failed request");
+        };
+
+        assert_eq!(result, status);
+    }
+
+    #[test]
+    fn test_reindeer_optimize_pool_too_large() {
+        let body = format!(
+            r#"{{
+                "pool": [{}],
+                "target": "speed",
+                "constraint": {{"field": "cAnD13s_3ATeN-yesT3rdAy", "limit": 20}},
+                "size": 5
+            }}"#,
+            optimize_pool(MAX_OPTIMIZE_POOL + 1)
+        );
+        let request = serde_json::from_str(&body).unwrap();
+        let TeamError::Failed((status, _)) = reindeer_optimize(Ok(TeamBody(request))).unwrap_err()
+        else {
+            panic!("expected a failed request");
+        };
+
+        assert_eq!(status, Status::BadRequest);
+    }
+
+    #[rstest]
+    #[case(&[5.0, 6.0, 4.0, 7.0], 2, 0.0)]
+    #[case(&[8.0, 7.0, 6.0, 5.0, 4.0], 2, 0.0)]
+    #[case(&[3.0, 3.0, 2.0, 2.0, 2.0], 2, 0.0)]
+    #[case(&[10.0, 1.0, 1.0], 2, 8.0)]
+    #[case(&[1.0, 2.0], 3, 2.0)]
+    fn test_balance_teams(#[case] values: &[f64], #[case] teams: usize, #[case] gap: f64) {
+        let assignment = balance_teams(values, teams);
+        let mut totals = vec![0.0; teams];
+
+        for (value, team) in values.iter().zip(assignment) {
+            totals[team] += value;
+        }
+
+        let max = totals.iter().cloned().fold(f64::MIN, f64::max);
+        let min = totals.iter().cloned().fold(f64::MAX, f64::min);
+
+        assert_eq!(max - min, gap);
+    }
+
+    #[test]
+    fn test_reindeer_partition() {
+        let request = serde_json::from_str(
+            r#"{
+                "pool": [
+                    {"name": "Dasher", "strength": 5},
+                    {"name": "Dancer", "strength": 6},
+                    {"name": "Prancer", "strength": 4},
+                    {"name": "Vixen", "strength": 7}
+                ],
+                "teams": 2
+            }"#,
+        )
+        .unwrap();
+        let Json(result) = reindeer_partition(Ok(TeamBody(request))).unwrap();
+        let teams: Vec<(Vec<&str>, f64)> = result
+            .teams
+            .iter()
+            .map(|team| (team.members.iter().map(|r| r.name).collect(), team.total))
+            .collect();
+
+        assert_eq!(
+            teams,
+            [
+                (vec!["Prancer", "Vixen"], 11.0),
+                (vec!["Dasher", "Dancer"], 11.0)
+            ]
+        );
+        assert_eq!(result.gap, 0.0);
+    }
+
+    #[rstest]
+    #[case(r#"{"pool": [], "teams": 0}"#)]
+    #[case(r#"{"pool": [{"name": "Dasher"}], "teams": 2}"#)]
+    #[case(r#"{"pool": [{"name": "Dasher"}], "teams": 18446744073709551615}"#)]
+    #[case(r#"{"pool": [{"name": "Dasher"}], "teams": 1, "field": "favorite_food"}"#)]
+    fn test_reindeer_partition_error(#[case] body: &str) {
+        let request = serde_json::from_str(body).unwrap();
+        let TeamError::Failed((status, _)) = reindeer_partition(Ok(TeamBody(request))).unwrap_err()
+        else {
+            panic!("expected a failed request");
+        };
+
+        assert_eq!(status, Status::BadRequest);
+    }
+
+    #[test]
+    fn test_reindeer_partition_pool_too_large() {
+        let body = format!(
+            r#"{{"pool": [{}], "teams": 2}}"#,
+            optimize_pool(MAX_PARTITION_POOL + 1)
+        );
+        let request = serde_json::from_str(&body).unwrap();
+        let TeamError::Failed((status, message)) =
+            reindeer_partition(Ok(TeamBody(request))).unwrap_err()
+        else {
+            panic!("expected a failed request");
+        };
+
+        assert_eq!(status, Status::BadRequest);
+        assert_eq!(message, "Too many reindeer, the limit is 500");
+
+        let body = format!(
+            r#"{{"pool": [{}], "teams": 7, "field": "speed"}}"#,
+            optimize_pool(MAX_PARTITION_POOL)
+        );
+        let request = serde_json::from_str(&body).unwrap();
+
+        assert!(reindeer_partition(Ok(TeamBody(request))).is_ok());
+    }
+
+    #[test]
+    fn test_reindeer_partition_strict() {
+        let body = r#"{"pool": [{"name": "Dasher", "strength": 5, "nose": "red"}], "teams": 1}"#;
+        let rejection = reindeer_partition(TeamBody::parse(body, true)).unwrap_err();
+        let TeamError::Rejected(TeamRejection(status, errors)) = rejection else {
+            panic!("expected a rejected payload");
+        };
+        let fields: Vec<_> = errors.iter().map(|err| err.field.as_deref()).collect();
+
+        assert_eq!(status, Status::UnprocessableEntity);
+        assert_eq!(errors[0].index, Some(0));
+        assert_eq!(fields[..3], [Some("nose"), Some("speed"), Some("height")]);
+        assert!(reindeer_partition(TeamBody::parse(body, false)).is_ok());
+    }
+
+    /// Runs against the database in `TEST_DATABASE_URL`, whose roster tables
+    /// it drops and recreates; skipped when the variable is not set.
+    #[rocket::async_test]
+    async fn test_roster_concurrent_updates() {
+        let Ok(url) = std::env::var("TEST_DATABASE_URL") else {
+            return;
+        };
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .max_connections(8)
+            .connect(&url)
+            .await
+            .unwrap();
+        let gift_db = crate::cch23::create_gift_db(pool);
+        let state = State::from(&gift_db);
+        let body = r#"[{"name": "Dasher", "strength": 5}]"#;
+
+        reset_rosters(state).await.unwrap();
+        let Json(created) = create_roster(TeamBody::parse(body, false), state)
+            .await
+            .unwrap();
+        let updates =
+            (0..16).map(|_| update_roster(created.id, TeamBody::parse(body, false), state));
+        let mut versions: Vec<i32> = rocket::futures::future::join_all(updates)
+            .await
+            .into_iter()
+            .map(|result| result.unwrap().0.version)
+            .collect();
+        versions.sort_unstable();
+
+        assert_eq!(created.version, 1);
+        assert_eq!(versions, (2..=17).collect::<Vec<_>>());
+    }
 }
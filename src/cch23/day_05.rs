@@ -1,23 +1,908 @@
-use rocket::post;
-use rocket::serde::json::Json;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 
-#[post("/?<offset>&<limit>&<split>", data = "<names>")]
-fn name_pager(
+use base64::engine::{general_purpose::URL_SAFE_NO_PAD, Engine};
+use regex::Regex;
+use rocket::http::uri::Origin;
+use rocket::http::{Header, RawStr, Status};
+use rocket::request::{self, FromRequest};
+use rocket::response::{Responder, Response};
+use rocket::serde::uuid::Uuid;
+use rocket::serde::{json::Json, Deserialize, Serialize};
+use rocket::tokio::sync::RwLock;
+use rocket::{delete, get, post, Either, FromForm, FromFormField, Request, State};
+use serde_json::Value;
+use ulid::Ulid;
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+use crate::cch23::GiftDatabase;
+
+#[derive(Debug, Serialize)]
+struct PagerError {
+    error: String,
+}
+
+type PagerErrorResponse = (Status, Json<PagerError>);
+
+macro_rules! pager_err {
+    (status = $status:expr, $($arg:tt)*) => {
+        ($status, Json(PagerError { error: format!($($arg)*) }))
+    };
+    ($($arg:tt)*) => {
+        pager_err!(status = Status::BadRequest, $($arg)*)
+    };
+}
+
+macro_rules! server_err {
+    ($err:expr) => {
+        pager_err!(status = Status::InternalServerError, "{}", $err)
+    };
+}
+
+/// Opaque position in the list, handed out as the `next` and `prev` links.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Cursor(usize);
+
+impl Cursor {
+    fn encode(self) -> String {
+        URL_SAFE_NO_PAD.encode(format!("offset:{}", self.0))
+    }
+
+    fn decode(token: &str) -> Option<Cursor> {
+        let bytes = URL_SAFE_NO_PAD.decode(token).ok()?;
+        let text = String::from_utf8(bytes).ok()?;
+        text.strip_prefix("offset:")?.parse().ok().map(Cursor)
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct Page {
+    items: Value,
+    total: usize,
+    filtered: usize,
+    next: Option<String>,
+    prev: Option<String>,
+}
+
+/// Take `limit` names from `offset`, or `limit` chunks of `split` names when splitting.
+fn paginate(
+    names: &[String],
+    offset: usize,
+    limit: Option<usize>,
+    split: Option<usize>,
+) -> Result<Page, PagerErrorResponse> {
+    let total = names.len();
+
+    if offset > total {
+        return Err(pager_err!(
+            "Offset {offset} is out of range for {total} names"
+        ));
+    }
+
+    let rest = &names[offset..];
+    let items = match split {
+        Some(0) => return Err(pager_err!("Split must be positive")),
+        Some(split) => rest
+            .chunks(split)
+            .take(limit.unwrap_or(usize::MAX))
+            .collect(),
+        None => rest
+            .iter()
+            .take(limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect(),
+    };
+    let step = limit.map(|limit| limit.saturating_mul(split.unwrap_or(1)));
+    let next = match step {
+        Some(step) if step > 0 && offset.saturating_add(step) < total => {
+            Some(Cursor(offset + step).encode())
+        }
+        _ => None,
+    };
+    let prev = match step {
+        _ if offset == 0 => None,
+        Some(step) => Some(Cursor(offset.saturating_sub(step)).encode()),
+        None => Some(Cursor(0).encode()),
+    };
+
+    Ok(Page {
+        items,
+        total,
+        filtered: 0,
+        next,
+        prev,
+    })
+}
+
+/// A page of names, with RFC 8288 `Link` headers to its neighbours.
+struct PagerResponse {
+    page: Page,
+    envelope: bool,
+    links: Vec<String>,
+    etag: Option<String>,
+}
+
+impl<'r> Responder<'r, 'static> for PagerResponse {
+    fn respond_to(self, request: &'r Request<'_>) -> rocket::response::Result<'static> {
+        let filtered = self.page.filtered;
+        let body = if self.envelope {
+            Json(serde_json::to_value(self.page).map_err(|_| Status::InternalServerError)?)
+        } else {
+            Json(self.page.items)
+        };
+        let mut response = Response::build_from(body.respond_to(request)?);
+
+        if !self.links.is_empty() {
+            response.header(Header::new("Link", self.links.join(", ")));
+        }
+
+        if let Some(etag) = self.etag {
+            response.header(Header::new("ETag", etag));
+        }
+
+        response.header(Header::new("X-Filtered-Count", filtered.to_string()));
+
+        response.ok()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, FromFormField)]
+enum SortMode {
+    Lexical,
+    Natural,
+    /// Ignore case and accents, which is no collation for any particular locale.
+    #[field(value = "accent_insensitive")]
+    AccentInsensitive,
+}
+
+impl SortMode {
+    /// The value of this mode in the `sort` query parameter.
+    fn as_str(self) -> &'static str {
+        match self {
+            SortMode::Lexical => "lexical",
+            SortMode::Natural => "natural",
+            SortMode::AccentInsensitive => "accent_insensitive",
+        }
+    }
+
+    fn compare(self, a: &str, b: &str) -> Ordering {
+        match self {
+            SortMode::Lexical => a.cmp(b),
+            SortMode::Natural => natural_cmp(a, b),
+            SortMode::AccentInsensitive => accent_insensitive_key(a)
+                .cmp(&accent_insensitive_key(b))
+                .then_with(|| a.cmp(b)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, FromFormField)]
+enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+/// Compare runs of digits by their numeric value, so that `Elf 9` sorts before `Elf 10`.
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a_rest, mut b_rest) = (a, b);
+
+    loop {
+        let (Some(x), Some(y)) = (a_rest.chars().next(), b_rest.chars().next()) else {
+            return a_rest.len().cmp(&b_rest.len()).then_with(|| a.cmp(b));
+        };
+
+        if x.is_ascii_digit() && y.is_ascii_digit() {
+            let a_end = a_rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(a_rest.len());
+            let b_end = b_rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(b_rest.len());
+            let a_digits = a_rest[..a_end].trim_start_matches('0');
+            let b_digits = b_rest[..b_end].trim_start_matches('0');
+            let ordering = a_digits
+                .len()
+                .cmp(&b_digits.len())
+                .then_with(|| a_digits.cmp(b_digits));
+
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+
+            (a_rest, b_rest) = (&a_rest[a_end..], &b_rest[b_end..]);
+        } else {
+            if x != y {
+                return x.cmp(&y);
+            }
+
+            (a_rest, b_rest) = (&a_rest[x.len_utf8()..], &b_rest[y.len_utf8()..]);
+        }
+    }
+}
+
+/// Fold case and strip accents, so that `Élan` sorts next to `elan`.
+fn accent_insensitive_key(name: &str) -> String {
+    name.nfkd()
+        .filter(|&c| !is_combining_mark(c))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Paging options shared by the posted and the stored name lists.
+///
+/// Names are deduplicated, filtered and sorted before they are paged.
+#[derive(Debug, Default, FromForm)]
+struct PageQuery<'r> {
     offset: Option<usize>,
     limit: Option<usize>,
     split: Option<usize>,
+    cursor: Option<&'r str>,
+    envelope: Option<bool>,
+    sort: Option<SortMode>,
+    order: Option<SortOrder>,
+    prefix: Option<&'r str>,
+    contains: Option<&'r str>,
+    regex: Option<&'r str>,
+    unique: Option<bool>,
+}
+
+impl PageQuery<'_> {
+    /// Apply the filters, `unique` and the sort, returning the names left.
+    fn select<'n>(&self, names: &'n [String]) -> Result<Vec<&'n String>, PagerErrorResponse> {
+        let regex = match self.regex {
+            Some(pattern) => {
+                Some(Regex::new(pattern).map_err(|err| pager_err!("Invalid regex: {err}"))?)
+            }
+            None => None,
+        };
+        let mut seen = HashSet::new();
+        let mut selected: Vec<&String> = names
+            .iter()
+            .filter(|name| self.prefix.map_or(true, |prefix| name.starts_with(prefix)))
+            .filter(|name| self.contains.map_or(true, |needle| name.contains(needle)))
+            .filter(|name| regex.as_ref().map_or(true, |regex| regex.is_match(name)))
+            .filter(|name| !self.unique.unwrap_or_default() || seen.insert(name.as_str()))
+            .collect();
+
+        if let Some(sort) = self.sort {
+            selected.sort_by(|a, b| sort.compare(a, b));
+        }
+
+        if self.order == Some(SortOrder::Desc) {
+            selected.reverse();
+        }
+
+        Ok(selected)
+    }
+
+    /// The options to carry over into the `Link` headers, percent-encoded.
+    fn link_options(&self) -> String {
+        let mut options = Vec::new();
+
+        if let Some(limit) = self.limit {
+            options.push(format!("limit={limit}"));
+        }
+
+        if let Some(split) = self.split {
+            options.push(format!("split={split}"));
+        }
+
+        if let Some(sort) = self.sort {
+            options.push(format!("sort={}", sort.as_str()));
+        }
+
+        if let Some(order) = self.order {
+            options.push(format!("order={}", format!("{order:?}").to_lowercase()));
+        }
+
+        for (key, value) in [
+            ("prefix", self.prefix),
+            ("contains", self.contains),
+            ("regex", self.regex),
+        ] {
+            if let Some(value) = value {
+                options.push(format!("{key}={}", RawStr::new(value).percent_encode()));
+            }
+        }
+
+        if self.unique.unwrap_or_default() {
+            options.push(String::from("unique=true"));
+        }
+
+        if self.envelope.unwrap_or_default() {
+            options.push(String::from("envelope=true"));
+        }
+
+        options
+            .iter()
+            .fold(String::new(), |query, option| query + "&" + option)
+    }
+
+    fn respond(
+        &self,
+        names: &[String],
+        origin: &Origin<'_>,
+    ) -> Result<PagerResponse, PagerErrorResponse> {
+        let offset = match (self.cursor, self.offset) {
+            (Some(_), Some(_)) => return Err(pager_err!("Use either a cursor or an offset")),
+            (Some(token), None) => {
+                Cursor::decode(token)
+                    .ok_or_else(|| pager_err!("Invalid cursor"))?
+                    .0
+            }
+            (None, offset) => offset.unwrap_or_default(),
+        };
+        let selected: Vec<String> = self.select(names)?.into_iter().cloned().collect();
+        let mut page = paginate(&selected, offset, self.limit, self.split)?;
+        page.filtered = names.len() - selected.len();
+
+        let options = self.link_options();
+        let links = [(&page.next, "next"), (&page.prev, "prev")]
+            .into_iter()
+            .filter_map(|(cursor, rel)| {
+                let cursor = cursor.as_deref()?;
+                Some(format!(
+                    r#"<{}?cursor={cursor}{options}>; rel="{rel}""#,
+                    origin.path()
+                ))
+            })
+            .collect();
+
+        Ok(PagerResponse {
+            page,
+            envelope: self.envelope.unwrap_or_default(),
+            links,
+            etag: None,
+        })
+    }
+}
+
+/// Pages through the posted names.
+///
+/// Follow-up pages are requested with the opaque `cursor` from the `Link` header, or from the
+/// `{ items, total, filtered, next, prev }` body returned when `envelope` is set. The number of
+/// names dropped by the filters and `unique` is also sent as `X-Filtered-Count`.
+#[post("/?<query..>", data = "<names>")]
+fn name_pager(
+    query: PageQuery<'_>,
+    names: Json<Vec<String>>,
+    origin: &Origin<'_>,
+) -> Result<PagerResponse, PagerErrorResponse> {
+    query.respond(&names, origin)
+}
+
+/// Where uploaded name lists are kept, read from the `name_lists` config key.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NameListBackend {
+    #[default]
+    Memory,
+    Postgres,
+}
+
+#[derive(Debug, PartialEq)]
+struct StoredList {
+    names: Vec<String>,
+    etag: String,
+}
+
+impl StoredList {
+    fn new(names: Vec<String>) -> StoredList {
+        let digest = sha256::digest(serde_json::to_string(&names).unwrap_or_default());
+
+        StoredList {
+            names,
+            etag: format!(r#""{digest}""#),
+        }
+    }
+}
+
+/// Most lists the memory backend keeps, since nothing ever evicts them.
+const MAX_MEMORY_LISTS: usize = 1000;
+
+/// Most names in a single list kept by the memory backend.
+const MAX_MEMORY_LIST_NAMES: usize = 10_000;
+
+pub struct NameLists {
+    backend: NameListBackend,
+    memory: RwLock<HashMap<Uuid, Arc<StoredList>>>,
+}
+
+impl NameLists {
+    async fn insert(
+        &self,
+        gift_db: &GiftDatabase,
+        list: StoredList,
+    ) -> Result<(Uuid, Arc<StoredList>), PagerErrorResponse> {
+        let id: Uuid = Ulid::new().into();
+        let list = Arc::new(list);
+
+        match self.backend {
+            NameListBackend::Memory => {
+                if list.names.len() > MAX_MEMORY_LIST_NAMES {
+                    return Err(pager_err!(
+                        status = Status::PayloadTooLarge,
+                        "A name list holds at most {MAX_MEMORY_LIST_NAMES} names"
+                    ));
+                }
+
+                let mut memory = self.memory.write().await;
+
+                if memory.len() >= MAX_MEMORY_LISTS {
+                    return Err(pager_err!(
+                        status = Status::InsufficientStorage,
+                        "No room for more than {MAX_MEMORY_LISTS} name lists"
+                    ));
+                }
+
+                memory.insert(id, list.clone());
+            }
+            NameListBackend::Postgres => {
+                let _result =
+                    sqlx::query("INSERT INTO name_lists (id, names, etag) VALUES ($1, $2, $3)")
+                        .bind(id.to_string())
+                        .bind(&list.names)
+                        .bind(&list.etag)
+                        .execute(&gift_db.pool)
+                        .await
+                        .map_err(|err| server_err!(err))?;
+            }
+        }
+
+        Ok((id, list))
+    }
+
+    async fn get(
+        &self,
+        gift_db: &GiftDatabase,
+        id: Uuid,
+    ) -> Result<Arc<StoredList>, PagerErrorResponse> {
+        let list = match self.backend {
+            NameListBackend::Memory => self.memory.read().await.get(&id).cloned(),
+            NameListBackend::Postgres => sqlx::query_as::<_, (Vec<String>, String)>(
+                "SELECT names, etag FROM name_lists WHERE id = $1",
+            )
+            .bind(id.to_string())
+            .fetch_optional(&gift_db.pool)
+            .await
+            .map_err(|err| server_err!(err))?
+            .map(|(names, etag)| Arc::new(StoredList { names, etag })),
+        };
+
+        list.ok_or_else(|| pager_err!(status = Status::NotFound, "Name list {id} was not found"))
+    }
+
+    async fn remove(&self, gift_db: &GiftDatabase, id: Uuid) -> Result<(), PagerErrorResponse> {
+        let removed = match self.backend {
+            NameListBackend::Memory => self.memory.write().await.remove(&id).is_some(),
+            NameListBackend::Postgres => {
+                sqlx::query("DELETE FROM name_lists WHERE id = $1")
+                    .bind(id.to_string())
+                    .execute(&gift_db.pool)
+                    .await
+                    .map_err(|err| server_err!(err))?
+                    .rows_affected()
+                    > 0
+            }
+        };
+
+        if removed {
+            Ok(())
+        } else {
+            Err(pager_err!(
+                status = Status::NotFound,
+                "Name list {id} was not found"
+            ))
+        }
+    }
+}
+
+/// The `If-None-Match` request header, if any.
+struct IfNoneMatch<'r>(Option<&'r str>);
+
+impl IfNoneMatch<'_> {
+    fn matches(&self, etag: &str) -> bool {
+        self.0.is_some_and(|value| {
+            value
+                .split(',')
+                .map(str::trim)
+                .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+        })
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for IfNoneMatch<'r> {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        request::Outcome::Success(IfNoneMatch(request.headers().get_one("If-None-Match")))
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct StoredListInfo {
+    id: Uuid,
+    etag: String,
+    total: usize,
+}
+
+#[derive(Responder)]
+#[response(status = 201)]
+struct ListCreated {
+    info: Json<StoredListInfo>,
+    location: Header<'static>,
+    etag: Header<'static>,
+}
+
+#[derive(Responder)]
+#[response(status = 304)]
+struct NotModified {
+    inner: (),
+    etag: Header<'static>,
+}
+
+#[post("/lists/reset")]
+async fn reset_lists(
+    lists: &State<NameLists>,
+    gift_db: &State<GiftDatabase>,
+) -> Result<(), PagerErrorResponse> {
+    lists.memory.write().await.clear();
+
+    if lists.backend == NameListBackend::Postgres {
+        let mut transaction = (gift_db.pool.begin())
+            .await
+            .map_err(|err| server_err!(err))?;
+        let _result = sqlx::query("DROP TABLE IF EXISTS name_lists")
+            .execute(&mut *transaction)
+            .await
+            .map_err(|err| server_err!(err))?;
+        let _result = sqlx::query(
+            r#"CREATE TABLE name_lists (
+                id TEXT PRIMARY KEY,
+                names TEXT[] NOT NULL,
+                etag TEXT NOT NULL
+            )"#,
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|err| server_err!(err))?;
+
+        transaction.commit().await.map_err(|err| server_err!(err))?;
+    }
+
+    Ok(())
+}
+
+/// Stores the posted names, to be paged through with [`stored_name_pager`].
+#[post("/lists", data = "<names>")]
+async fn upload_list(
     names: Json<Vec<String>>,
-) -> Json<serde_json::Value> {
-    let offset = offset.unwrap_or_default();
-    let limit = limit.unwrap_or(usize::MAX);
+    lists: &State<NameLists>,
+    gift_db: &State<GiftDatabase>,
+    origin: &Origin<'_>,
+) -> Result<ListCreated, PagerErrorResponse> {
+    let (id, list) = lists.insert(gift_db, StoredList::new(names.0)).await?;
 
-    if let Some(split) = split {
-        Json(names[offset..].chunks(split).take(limit).collect())
-    } else {
-        Json(names.iter().skip(offset).take(limit).cloned().collect())
+    Ok(ListCreated {
+        location: Header::new("Location", format!("{}/{id}", origin.path())),
+        etag: Header::new("ETag", list.etag.clone()),
+        info: Json(StoredListInfo {
+            id,
+            etag: list.etag.clone(),
+            total: list.names.len(),
+        }),
+    })
+}
+
+/// Pages through a stored list, taking the same options as [`name_pager`].
+///
+/// Stored lists never change, so a matching `If-None-Match` is answered with `304 Not Modified`.
+#[get("/lists/<id>?<query..>")]
+async fn stored_name_pager(
+    id: Uuid,
+    query: PageQuery<'_>,
+    if_none_match: IfNoneMatch<'_>,
+    lists: &State<NameLists>,
+    gift_db: &State<GiftDatabase>,
+    origin: &Origin<'_>,
+) -> Result<Either<PagerResponse, NotModified>, PagerErrorResponse> {
+    let list = lists.get(gift_db, id).await?;
+
+    if if_none_match.matches(&list.etag) {
+        return Ok(Either::Right(NotModified {
+            inner: (),
+            etag: Header::new("ETag", list.etag.clone()),
+        }));
+    }
+
+    let mut response = query.respond(&list.names, origin)?;
+    response.etag = Some(list.etag.clone());
+
+    Ok(Either::Left(response))
+}
+
+#[delete("/lists/<id>")]
+async fn delete_list(
+    id: Uuid,
+    lists: &State<NameLists>,
+    gift_db: &State<GiftDatabase>,
+) -> Result<(), PagerErrorResponse> {
+    lists.remove(gift_db, id).await
+}
+
+pub fn create_name_lists(backend: NameListBackend) -> NameLists {
+    NameLists {
+        backend,
+        memory: RwLock::new(HashMap::new()),
     }
 }
 
 pub fn routes() -> Vec<rocket::Route> {
-    rocket::routes![name_pager]
+    rocket::routes![
+        name_pager,
+        reset_lists,
+        upload_list,
+        stored_name_pager,
+        delete_list
+    ]
+}
+
+#[cfg(test)]
+mod tests_day_05 {
+    use super::*;
+    use rocket::form::{FromFormField, ValueField};
+    use rstest::*;
+
+    fn names(count: usize) -> Vec<String> {
+        (0..count).map(|i| format!("Name {i}")).collect()
+    }
+
+    #[rstest]
+    #[case(0, Some(2), None, serde_json::json!(["Name 0", "Name 1"]), Some(2), None)]
+    #[case(2, Some(2), None, serde_json::json!(["Name 2", "Name 3"]), Some(4), Some(0))]
+    #[case(4, Some(2), None, serde_json::json!(["Name 4"]), None, Some(2))]
+    #[case(1, None, Some(2), serde_json::json!([["Name 1", "Name 2"], ["Name 3", "Name 4"]]), None, Some(0))]
+    #[case(0, Some(1), Some(2), serde_json::json!([["Name 0", "Name 1"]]), Some(2), None)]
+    #[case(5, Some(3), Some(2), serde_json::json!([]), None, Some(0))]
+    fn test_paginate(
+        #[case] offset: usize,
+        #[case] limit: Option<usize>,
+        #[case] split: Option<usize>,
+        #[case] items: Value,
+        #[case] next: Option<usize>,
+        #[case] prev: Option<usize>,
+    ) {
+        let page = paginate(&names(5), offset, limit, split).unwrap();
+        let expected = Page {
+            items,
+            total: 5,
+            filtered: 0,
+            next: next.map(|offset| Cursor(offset).encode()),
+            prev: prev.map(|offset| Cursor(offset).encode()),
+        };
+
+        assert_eq!(page, expected);
+    }
+
+    #[rstest]
+    #[case(6, None, "Offset 6 is out of range for 5 names")]
+    #[case(6, Some(2), "Offset 6 is out of range for 5 names")]
+    #[case(0, Some(0), "Split must be positive")]
+    fn test_paginate_error(
+        #[case] offset: usize,
+        #[case] split: Option<usize>,
+        #[case] message: &str,
+    ) {
+        let (status, Json(err)) = paginate(&names(5), offset, None, split).unwrap_err();
+
+        assert_eq!(status, Status::BadRequest);
+        assert_eq!(err.error, message);
+    }
+
+    #[rstest]
+    #[case(0)]
+    #[case(42)]
+    #[case(usize::MAX)]
+    fn test_cursor_round_trip(#[case] offset: usize) {
+        let token = Cursor(offset).encode();
+
+        assert_eq!(Cursor::decode(&token), Some(Cursor(offset)));
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("not a cursor")]
+    #[case("b2Zmc2V0Oi0x")]
+    fn test_cursor_invalid(#[case] token: &str) {
+        assert_eq!(Cursor::decode(token), None);
+    }
+
+    #[test]
+    fn test_stored_list_etag() {
+        let list = StoredList::new(names(3));
+
+        assert_eq!(list, StoredList::new(names(3)));
+        assert_ne!(list.etag, StoredList::new(names(4)).etag);
+        assert!(list.etag.starts_with('"') && list.etag.ends_with('"'));
+    }
+
+    #[rstest]
+    #[case(None, false)]
+    #[case(Some(r#""abc""#), true)]
+    #[case(Some(r#"W/"abc""#), true)]
+    #[case(Some(r#""xyz", "abc""#), true)]
+    #[case(Some("*"), true)]
+    #[case(Some(r#""xyz""#), false)]
+    #[case(Some("abc"), false)]
+    fn test_if_none_match(#[case] header: Option<&str>, #[case] expected: bool) {
+        assert_eq!(IfNoneMatch(header).matches(r#""abc""#), expected);
+    }
+
+    #[test]
+    fn test_page_query_contains() {
+        let names: Vec<String> = ["Dasher", "Dancer", "Prancer", "Vixen", "Comet"]
+            .map(String::from)
+            .into();
+        let query = PageQuery {
+            limit: Some(1),
+            envelope: Some(true),
+            contains: Some("ancer"),
+            ..Default::default()
+        };
+        let origin = Origin::parse("/5/lists/x").unwrap();
+        let response = query.respond(&names, &origin).unwrap();
+        let next = Cursor(1).encode();
+
+        assert_eq!(response.page.items, serde_json::json!(["Dancer"]));
+        assert_eq!(response.page.total, 2);
+        assert_eq!(
+            response.links,
+            vec![format!(
+                r#"</5/lists/x?cursor={next}&limit=1&contains=ancer&envelope=true>; rel="next""#
+            )]
+        );
+    }
+
+    fn reindeer() -> Vec<String> {
+        [
+            "Elf 10", "dasher", "Élan", "Elf 9", "Dancer", "elan", "Dasher", "Elf 09", "dasher",
+        ]
+        .map(String::from)
+        .into()
+    }
+
+    #[rstest]
+    #[case("Elf 9", "Elf 10", Ordering::Less)]
+    #[case("Elf 10", "Elf 9", Ordering::Greater)]
+    #[case("Elf 09", "Elf 9", Ordering::Less)]
+    #[case("a2b10", "a2b9", Ordering::Greater)]
+    #[case("a", "a1", Ordering::Less)]
+    #[case("b", "a10", Ordering::Greater)]
+    #[case(
+        "x100000000000000000000001",
+        "x100000000000000000000002",
+        Ordering::Less
+    )]
+    #[case("same 1", "same 1", Ordering::Equal)]
+    fn test_natural_cmp(#[case] a: &str, #[case] b: &str, #[case] expected: Ordering) {
+        assert_eq!(natural_cmp(a, b), expected);
+    }
+
+    #[rstest]
+    #[case(
+        PageQuery { sort: Some(SortMode::Lexical), ..Default::default() },
+        &["Dancer", "Dasher", "Elf 09", "Elf 10", "Elf 9", "dasher", "dasher", "elan", "Élan"]
+    )]
+    #[case(
+        PageQuery { sort: Some(SortMode::Natural), unique: Some(true), ..Default::default() },
+        &["Dancer", "Dasher", "Elf 09", "Elf 9", "Elf 10", "dasher", "elan", "Élan"]
+    )]
+    #[case(
+        PageQuery { sort: Some(SortMode::AccentInsensitive), unique: Some(true), ..Default::default() },
+        &["Dancer", "Dasher", "dasher", "elan", "Élan", "Elf 09", "Elf 10", "Elf 9"]
+    )]
+    #[case(
+        PageQuery { order: Some(SortOrder::Desc), ..Default::default() },
+        &["dasher", "Elf 09", "Dasher", "elan", "Dancer", "Elf 9", "Élan", "dasher", "Elf 10"]
+    )]
+    #[case(
+        PageQuery { prefix: Some("Elf"), sort: Some(SortMode::Natural), order: Some(SortOrder::Desc), ..Default::default() },
+        &["Elf 10", "Elf 9", "Elf 09"]
+    )]
+    #[case(
+        PageQuery { contains: Some("as"), unique: Some(true), ..Default::default() },
+        &["dasher", "Dasher"]
+    )]
+    #[case(
+        PageQuery { regex: Some("(?i)^[ée]la"), ..Default::default() },
+        &["Élan", "elan"]
+    )]
+    fn test_page_query_select(#[case] query: PageQuery<'_>, #[case] expected: &[&str]) {
+        let names = reindeer();
+        let selected = query.select(&names).unwrap();
+
+        assert_eq!(selected, expected);
+    }
+
+    #[test]
+    fn test_sort_mode_query_value() {
+        for sort in [
+            SortMode::Lexical,
+            SortMode::Natural,
+            SortMode::AccentInsensitive,
+        ] {
+            let field = ValueField::from_value(sort.as_str());
+
+            assert_eq!(SortMode::from_value(field), Ok(sort));
+        }
+
+        assert!(SortMode::from_value(ValueField::from_value("locale")).is_err());
+    }
+
+    #[test]
+    fn test_page_query_invalid_regex() {
+        let query = PageQuery {
+            regex: Some("(unclosed"),
+            ..Default::default()
+        };
+        let (status, Json(err)) = query.select(&reindeer()).unwrap_err();
+
+        assert_eq!(status, Status::BadRequest);
+        assert!(err.error.starts_with("Invalid regex"));
+    }
+
+    #[test]
+    fn test_page_query_filtered_count() {
+        let query = PageQuery {
+            prefix: Some("Elf"),
+            sort: Some(SortMode::Natural),
+            limit: Some(2),
+            regex: Some(r"\d+$"),
+            ..Default::default()
+        };
+        let origin = Origin::parse("/5").unwrap();
+        let response = query.respond(&reindeer(), &origin).unwrap();
+        let next = Cursor(2).encode();
+
+        assert_eq!(response.page.items, serde_json::json!(["Elf 09", "Elf 9"]));
+        assert_eq!(response.page.total, 3);
+        assert_eq!(response.page.filtered, 6);
+        assert_eq!(
+            response.links,
+            vec![format!(
+                r#"</5?cursor={next}&limit=2&sort=natural&prefix=Elf&regex=%5Cd%2B$>; rel="next""#
+            )]
+        );
+    }
+
+    #[rocket::async_test]
+    async fn test_memory_lists_are_bounded() {
+        // The memory backend never touches the database, so the pool never connects.
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .connect_lazy("postgres://localhost/unused")
+            .unwrap();
+        let gift_db = crate::cch23::create_gift_db(pool);
+        let lists = create_name_lists(NameListBackend::Memory);
+        let (status, Json(error)) = (lists.insert(&gift_db, StoredList::new(names(10_001))))
+            .await
+            .unwrap_err();
+
+        assert_eq!(status, Status::PayloadTooLarge);
+        assert_eq!(error.error, "A name list holds at most 10000 names");
+
+        for _ in 0..MAX_MEMORY_LISTS {
+            let list = StoredList::new(names(1));
+            assert!(lists.insert(&gift_db, list).await.is_ok());
+        }
+
+        let (status, _) = (lists.insert(&gift_db, StoredList::new(names(1))))
+            .await
+            .unwrap_err();
+
+        assert_eq!(status, Status::InsufficientStorage);
+        assert_eq!(lists.memory.read().await.len(), MAX_MEMORY_LISTS);
+    }
 }
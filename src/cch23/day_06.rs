@@ -1,5 +1,273 @@
-use rocket::post;
+use std::borrow::Cow;
+use std::iter;
+use std::sync::OnceLock;
+
+use aho_corasick::automaton::{Automaton, StateID};
+use aho_corasick::dfa::DFA;
+use aho_corasick::{Anchored, Input, MatchKind};
+use indexmap::IndexMap;
+use multer::{Constraints, Multipart, SizeLimit};
+use rocket::data::{self, Data, FromData, Limits, ToByteUnit};
+use rocket::http::Status;
 use rocket::serde::{json::Json, Deserialize, Serialize};
+use rocket::tokio::io::{AsyncRead, AsyncReadExt};
+use rocket::{post, FromForm, Request};
+use rocket_dyn_templates::{context, Template};
+use unicode_normalization::char::canonical_combining_class;
+use unicode_normalization::{is_nfkc_quick, IsNormalized, UnicodeNormalization};
+
+#[derive(Debug, Clone, Copy, PartialEq, FromForm)]
+struct MatchOptions {
+    /// Report every match, rather than the leftmost longest ones that do not overlap.
+    #[field(default_with = Some(true))]
+    overlapping: bool,
+    case_insensitive: bool,
+    whole_word: bool,
+    /// Compare the NFKC normal forms of the text and the patterns.
+    normalize: bool,
+}
+
+impl Default for MatchOptions {
+    fn default() -> Self {
+        MatchOptions {
+            overlapping: true,
+            case_insensitive: false,
+            whole_word: false,
+            normalize: false,
+        }
+    }
+}
+
+/// A match of the pattern at index `pattern`, as a byte range of the original text.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct PhraseMatch {
+    pattern: usize,
+    start: usize,
+    end: usize,
+}
+
+/// Text folded for matching, with the span of the original text each folded byte came from.
+struct FoldedText<'t> {
+    text: Cow<'t, str>,
+    spans: Option<Vec<(usize, usize)>>,
+}
+
+impl FoldedText<'_> {
+    fn original_range(&self, start: usize, end: usize) -> (usize, usize) {
+        match &self.spans {
+            Some(spans) => (spans[start].0, spans[end - 1].1),
+            None => (start, end),
+        }
+    }
+
+    fn is_whole_word(&self, start: usize, end: usize) -> bool {
+        let is_word_char = |c: char| c.is_alphanumeric() || c == '_';
+
+        !self.text[..start]
+            .chars()
+            .next_back()
+            .is_some_and(is_word_char)
+            && !self.text[end..].chars().next().is_some_and(is_word_char)
+    }
+}
+
+/// Whether NFKC never combines `c` with the text before it, so normalisation can restart there.
+///
+/// That holds when the decomposition of `c` starts with a starter that does not compose
+/// backwards, like the conjoining Hangul vowels do.
+fn is_composition_boundary(c: char) -> bool {
+    iter::once(c).nfkd().next().is_some_and(|first| {
+        canonical_combining_class(first) == 0
+            && is_nfkc_quick(iter::once(first)) != IsNormalized::Maybe
+    })
+}
+
+/// Apply NFKC and lowercasing as configured.
+///
+/// Normalisation works on runs that start at a composition boundary, so that every folded byte
+/// can be traced back to the run it came from, and the runs fold just like the whole text would.
+fn fold(text: &str, options: MatchOptions) -> FoldedText<'_> {
+    let MatchOptions {
+        case_insensitive,
+        normalize,
+        ..
+    } = options;
+
+    if !case_insensitive && !normalize {
+        return FoldedText {
+            text: Cow::Borrowed(text),
+            spans: None,
+        };
+    }
+
+    let mut folded = String::with_capacity(text.len());
+    let mut spans = Vec::with_capacity(text.len());
+    let mut run_start = 0;
+    let run_ends = (text.char_indices())
+        .filter(|&(index, c)| index > 0 && is_composition_boundary(c))
+        .map(|(index, _)| index)
+        .chain(iter::once(text.len()));
+
+    for run_end in run_ends {
+        let run = &text[run_start..run_end];
+        let folded_start = folded.len();
+        let chars: Box<dyn Iterator<Item = char>> = if normalize {
+            Box::new(run.nfkc())
+        } else {
+            Box::new(run.chars())
+        };
+
+        for c in chars {
+            if case_insensitive {
+                folded.extend(c.to_lowercase());
+            } else {
+                folded.push(c);
+            }
+        }
+
+        spans.extend(iter::repeat((run_start, run_end)).take(folded.len() - folded_start));
+        run_start = run_end;
+    }
+
+    FoldedText {
+        text: Cow::Owned(folded),
+        spans: Some(spans),
+    }
+}
+
+/// Finds any number of phrases in a single pass over the text.
+struct PhraseMatcher {
+    patterns: Vec<String>,
+    automaton: DFA,
+    options: MatchOptions,
+}
+
+impl PhraseMatcher {
+    fn new<P: AsRef<str>>(patterns: &[P], options: MatchOptions) -> Result<Self, String> {
+        let mut unique: Vec<String> = Vec::with_capacity(patterns.len());
+
+        for pattern in patterns.iter().map(AsRef::as_ref) {
+            if pattern.is_empty() {
+                return Err(String::from("Patterns must not be empty"));
+            }
+
+            if !unique.iter().any(|known| known == pattern) {
+                unique.push(String::from(pattern));
+            }
+        }
+
+        if unique.is_empty() {
+            return Err(String::from("At least one pattern is required"));
+        }
+
+        let folded = (unique.iter()).map(|pattern| fold(pattern, options).text.into_owned());
+        let kind = if options.overlapping {
+            MatchKind::Standard
+        } else {
+            MatchKind::LeftmostLongest
+        };
+        let automaton = DFA::builder()
+            .match_kind(kind)
+            .build(folded)
+            .map_err(|err| err.to_string())?;
+
+        Ok(PhraseMatcher {
+            patterns: unique,
+            automaton,
+            options,
+        })
+    }
+
+    /// Find the matches in `text`, ordered by where they start.
+    fn find(&self, text: &str) -> Vec<PhraseMatch> {
+        let folded = fold(text, self.options);
+        let input = Input::new(&*folded.text);
+        let matches: Vec<_> = if self.options.overlapping {
+            (self.automaton.try_find_overlapping_iter(input))
+                .expect("overlapping matchers use standard semantics")
+                .collect()
+        } else {
+            (self.automaton.try_find_iter(input))
+                .expect("the DFA supports unanchored searches")
+                .collect()
+        };
+
+        let mut matches: Vec<PhraseMatch> = matches
+            .into_iter()
+            .filter(|m| !self.options.whole_word || folded.is_whole_word(m.start(), m.end()))
+            .map(|m| {
+                let (start, end) = folded.original_range(m.start(), m.end());
+                PhraseMatch {
+                    pattern: m.pattern().as_usize(),
+                    start,
+                    end,
+                }
+            })
+            .collect();
+
+        matches.sort_by_key(|m| (m.start, m.end));
+        matches
+    }
+
+    fn count(&self, text: &str) -> Vec<usize> {
+        self.tally(&self.find(text))
+    }
+
+    /// Count matches in text that arrives in chunks, which only works with the default options.
+    ///
+    /// Folding and whole words need to see the text around a match, so they are left out.
+    fn streaming(&self) -> Option<StreamingCounter<'_>> {
+        (self.options == MatchOptions::default()).then(|| StreamingCounter::new(&self.automaton))
+    }
+
+    fn tally(&self, matches: &[PhraseMatch]) -> Vec<usize> {
+        let mut counts = vec![0; self.patterns.len()];
+
+        for phrase_match in matches {
+            counts[phrase_match.pattern] += 1;
+        }
+
+        counts
+    }
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+struct MatchPosition {
+    pattern: String,
+    offset: usize,
+    end: usize,
+    line: usize,
+    column: usize,
+}
+
+/// Add the 1-based line and column, counted in characters, to matches ordered by their start.
+fn locate(text: &str, patterns: &[String], matches: &[PhraseMatch]) -> Vec<MatchPosition> {
+    let (mut position, mut line, mut column) = (0, 1, 1);
+
+    matches
+        .iter()
+        .map(|m| {
+            for c in text[position..m.start].chars() {
+                if c == '\n' {
+                    line += 1;
+                    column = 1;
+                } else {
+                    column += 1;
+                }
+            }
+
+            position = m.start;
+
+            MatchPosition {
+                pattern: patterns[m.pattern].clone(),
+                offset: m.start,
+                end: m.end,
+                line,
+                column,
+            }
+        })
+        .collect()
+}
 
 #[derive(Debug, Deserialize, Serialize, PartialEq)]
 struct ElfCounter {
@@ -10,41 +278,381 @@ struct ElfCounter {
     shelf_without_elf: usize,
 }
 
+impl ElfCounter {
+    const PATTERNS: [&'static str; 3] = ["elf", "shelf", "elf on a shelf"];
+
+    /// The preset behind [`elf_on_a_shelf`], counting overlapping, case-sensitive matches.
+    fn matcher() -> &'static PhraseMatcher {
+        static MATCHER: OnceLock<PhraseMatcher> = OnceLock::new();
+
+        MATCHER.get_or_init(|| {
+            PhraseMatcher::new(&ElfCounter::PATTERNS, MatchOptions::default())
+                .expect("the elf patterns are valid")
+        })
+    }
+
+    fn streaming() -> StreamingCounter<'static> {
+        (ElfCounter::matcher().streaming()).expect("the elf preset uses the default options")
+    }
+
+    fn from_counts(counts: &[usize]) -> ElfCounter {
+        let &[elf, shelf, shelf_with_elf] = counts else {
+            unreachable!("the elf preset has three patterns");
+        };
+
+        ElfCounter {
+            elf,
+            shelf_with_elf,
+            shelf_without_elf: shelf - shelf_with_elf,
+        }
+    }
+}
+
+/// Counts overlapping matches in text that arrives in chunks.
+///
+/// The automaton state is carried from one chunk to the next, so matches that straddle a chunk
+/// boundary are found just as in a single pass.
+struct StreamingCounter<'m> {
+    dfa: &'m DFA,
+    state: StateID,
+    counts: Vec<usize>,
+    len: u64,
+}
+
+impl<'m> StreamingCounter<'m> {
+    /// Size of the chunks read from a stream.
+    const CHUNK_SIZE: usize = 64 * 1024;
+
+    fn new(dfa: &'m DFA) -> StreamingCounter<'m> {
+        StreamingCounter {
+            dfa,
+            state: (dfa.start_state(Anchored::No)).expect("the DFA supports unanchored searches"),
+            counts: vec![0; dfa.patterns_len()],
+            len: 0,
+        }
+    }
+
+    fn feed(&mut self, chunk: &[u8]) {
+        for &byte in chunk {
+            self.state = self.dfa.next_state(Anchored::No, self.state, byte);
+
+            if self.dfa.is_match(self.state) {
+                for index in 0..self.dfa.match_len(self.state) {
+                    self.counts[self.dfa.match_pattern(self.state, index).as_usize()] += 1;
+                }
+            }
+        }
+
+        self.len += chunk.len() as u64;
+    }
+
+    /// Feed everything from `reader`, failing once more than `limit` bytes have been read.
+    async fn read<R: AsyncRead + Unpin>(
+        mut self,
+        mut reader: R,
+        limit: u64,
+    ) -> Result<Vec<usize>, (Status, String)> {
+        let mut chunk = vec![0; StreamingCounter::CHUNK_SIZE];
+
+        loop {
+            let read = (reader.read(&mut chunk).await)
+                .map_err(|err| (Status::BadRequest, err.to_string()))?;
+
+            if read == 0 {
+                return Ok(self.counts);
+            }
+
+            self.feed(&chunk[..read]);
+
+            if self.len > limit {
+                return Err((
+                    Status::PayloadTooLarge,
+                    format!("Text is larger than the {limit} byte limit"),
+                ));
+            }
+        }
+    }
+}
+
+/// Limit on a streamed text body, overridden by the `elves` limit in `Rocket.toml`.
+const DEFAULT_TEXT_LIMIT: u64 = 64 * 1024 * 1024;
+
 #[post("/", data = "<text>")]
-fn elf_on_a_shelf(text: &str) -> Json<ElfCounter> {
-    let elf_count = text.matches("elf").count();
-    let shelf_count = text.matches("shelf").count();
-    let elf_on_a_shelf = b"elf on a shelf";
-    let shelf_with_elf = text
-        .as_bytes()
-        .windows(elf_on_a_shelf.len())
-        .filter(|window| window == elf_on_a_shelf)
-        .count();
-    let shelf_without_elf = shelf_count - shelf_with_elf;
-
-    Json(ElfCounter {
-        elf: elf_count,
-        shelf_with_elf,
-        shelf_without_elf,
-    })
+async fn elf_on_a_shelf(
+    text: Data<'_>,
+    limits: &Limits,
+) -> Result<Json<ElfCounter>, (Status, String)> {
+    let limit = (limits.get("elves")).map_or(DEFAULT_TEXT_LIMIT, |limit| limit.as_u64());
+    let stream = text.open(limit.saturating_add(1).bytes());
+    let counts = ElfCounter::streaming().read(stream, limit).await?;
+
+    Ok(Json(ElfCounter::from_counts(&counts)))
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+struct DocumentCount {
+    name: Option<String>,
+    #[serde(flatten)]
+    counter: ElfCounter,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+struct DocumentsReport {
+    documents: Vec<DocumentCount>,
+    total: ElfCounter,
+}
+
+/// Limit on a whole document upload, overridden by the `elf-documents` limit in `Rocket.toml`.
+const DEFAULT_DOCUMENTS_LIMIT: u64 = 128 * 1024 * 1024;
+
+/// The name and elf counts of each `document` of a multipart upload, counted as it streams in.
+///
+/// The upload is capped by its own `elf-documents` limit, rather than by the `file` and
+/// `data-form` limits that every other form shares.
+struct Documents(Vec<(Option<String>, Vec<usize>)>);
+
+impl Documents {
+    async fn read(request: &Request<'_>, data: Data<'_>) -> Result<Documents, (Status, String)> {
+        let boundary = (request.content_type())
+            .filter(|content_type| content_type.is_form_data())
+            .and_then(|content_type| content_type.param("boundary"))
+            .ok_or_else(|| {
+                let message = String::from("Expected a multipart form with a boundary");
+                (Status::UnsupportedMediaType, message)
+            })?;
+        let limit = (request.limits().get("elf-documents"))
+            .map_or(DEFAULT_DOCUMENTS_LIMIT, |limit| limit.as_u64());
+        let constraints = Constraints::new().size_limit(SizeLimit::new().whole_stream(limit));
+        let stream = data.open(limit.saturating_add(1).bytes());
+        let mut multipart = Multipart::with_reader_with_constraints(stream, boundary, constraints);
+        let multipart_err = |err: multer::Error| {
+            // Fields report the exceeded limit as a failure to read the stream.
+            let exceeded = match &err {
+                multer::Error::StreamSizeExceeded { .. } => true,
+                multer::Error::StreamReadFailed(cause) => matches!(
+                    cause.downcast_ref(),
+                    Some(multer::Error::StreamSizeExceeded { .. })
+                ),
+                _ => false,
+            };
+
+            if exceeded {
+                let message = format!("Upload is larger than the {limit} byte limit");
+                (Status::PayloadTooLarge, message)
+            } else {
+                (Status::BadRequest, err.to_string())
+            }
+        };
+        let mut documents = Vec::new();
+
+        while let Some(mut field) = multipart.next_field().await.map_err(multipart_err)? {
+            if field.name() != Some("document") {
+                continue;
+            }
+
+            // The name is only echoed back, so it is never used as a path.
+            let name = field.file_name().map(String::from);
+            let mut counter = ElfCounter::streaming();
+
+            while let Some(chunk) = field.chunk().await.map_err(multipart_err)? {
+                counter.feed(&chunk);
+            }
+
+            documents.push((name, counter.counts));
+        }
+
+        Ok(Documents(documents))
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromData<'r> for Documents {
+    type Error = (Status, String);
+
+    async fn from_data(request: &'r Request<'_>, data: Data<'r>) -> data::Outcome<'r, Self> {
+        match Documents::read(request, data).await {
+            Ok(documents) => data::Outcome::Success(documents),
+            Err(err) => data::Outcome::Error((err.0, err)),
+        }
+    }
+}
+
+/// Counts each `document` of a multipart upload, as well as all of them together.
+#[post("/documents", data = "<documents>")]
+fn count_documents(
+    documents: Result<Documents, (Status, String)>,
+) -> Result<Json<DocumentsReport>, (Status, String)> {
+    let Documents(documents) = documents?;
+    let mut total = vec![0; ElfCounter::PATTERNS.len()];
+
+    for (_, counts) in &documents {
+        for (total, count) in total.iter_mut().zip(counts) {
+            *total += count;
+        }
+    }
+
+    Ok(Json(DocumentsReport {
+        documents: (documents.into_iter())
+            .map(|(name, counts)| DocumentCount {
+                name,
+                counter: ElfCounter::from_counts(&counts),
+            })
+            .collect(),
+        total: ElfCounter::from_counts(&total),
+    }))
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+struct PhraseCounts {
+    counts: IndexMap<String, usize>,
+    total: usize,
+}
+
+/// Counts each `pattern` in the posted text, see [`MatchOptions`] for the other parameters.
+#[post("/count?<pattern>&<options..>", data = "<text>")]
+fn count_phrases(
+    pattern: Vec<&str>,
+    options: MatchOptions,
+    text: &str,
+) -> Result<Json<PhraseCounts>, (Status, String)> {
+    let matcher = PhraseMatcher::new(&pattern, options).map_err(|err| (Status::BadRequest, err))?;
+    let counts = matcher.count(text);
+
+    Ok(Json(PhraseCounts {
+        total: counts.iter().sum(),
+        counts: matcher.patterns.into_iter().zip(counts).collect(),
+    }))
+}
+
+/// Lists where each `pattern` occurs in the posted text.
+#[post("/matches?<pattern>&<options..>", data = "<text>")]
+fn match_positions(
+    pattern: Vec<&str>,
+    options: MatchOptions,
+    text: &str,
+) -> Result<Json<Vec<MatchPosition>>, (Status, String)> {
+    let matcher = PhraseMatcher::new(&pattern, options).map_err(|err| (Status::BadRequest, err))?;
+    let matches = matcher.find(text);
+
+    Ok(Json(locate(text, &matcher.patterns, &matches)))
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+struct Segment<'t> {
+    text: &'t str,
+    class: Option<&'static str>,
+}
+
+/// Split the text into plain runs, "elf on a shelf" runs and shelves with no elf on them.
+///
+/// Overlapping "elf on a shelf" matches are merged into a single run, and shelves overlapping a
+/// run are left out of it.
+fn highlight<'t>(text: &'t str, matches: &[PhraseMatch]) -> Vec<Segment<'t>> {
+    const SHELF: usize = 1;
+    const ELF_ON_A_SHELF: usize = 2;
+
+    let mut runs: Vec<(usize, usize, &'static str)> = Vec::new();
+
+    for m in matches.iter().filter(|m| m.pattern == ELF_ON_A_SHELF) {
+        match runs.last_mut() {
+            Some((_, end, _)) if m.start <= *end => *end = (*end).max(m.end),
+            _ => runs.push((m.start, m.end, "elf-on-a-shelf")),
+        }
+    }
+
+    let bare_shelves: Vec<_> = (matches.iter())
+        .filter(|m| m.pattern == SHELF)
+        .filter(|m| {
+            !runs
+                .iter()
+                .any(|&(start, end, _)| start < m.end && m.start < end)
+        })
+        .map(|m| (m.start, m.end, "bare-shelf"))
+        .collect();
+
+    runs.extend(bare_shelves);
+    runs.sort_unstable();
+
+    let mut segments = Vec::new();
+    let mut position = 0;
+
+    for (start, end, class) in runs {
+        if position < start {
+            segments.push(Segment {
+                text: &text[position..start],
+                class: None,
+            });
+        }
+
+        segments.push(Segment {
+            text: &text[start..end],
+            class: Some(class),
+        });
+        position = end;
+    }
+
+    if position < text.len() {
+        segments.push(Segment {
+            text: &text[position..],
+            class: None,
+        });
+    }
+
+    segments
+}
+
+/// Renders the posted text with every "elf on a shelf" and every bare shelf highlighted.
+#[post("/highlight?<options..>", data = "<text>")]
+fn highlight_shelves(options: MatchOptions, text: &str) -> Result<Template, (Status, String)> {
+    let matcher = PhraseMatcher::new(&ElfCounter::PATTERNS, options)
+        .map_err(|err| (Status::BadRequest, err))?;
+    let matches = matcher.find(text);
+    let counts = matcher.tally(&matches);
+
+    Ok(Template::render(
+        "day_06",
+        context! {
+            counter: ElfCounter::from_counts(&counts),
+            segments: highlight(text, &matches),
+        },
+    ))
 }
 
 pub fn routes() -> Vec<rocket::Route> {
-    rocket::routes![elf_on_a_shelf]
+    rocket::routes![
+        elf_on_a_shelf,
+        count_documents,
+        count_phrases,
+        match_positions,
+        highlight_shelves
+    ]
 }
 
 #[cfg(test)]
 mod tests_day_06 {
     use super::*;
+    use rocket::http::ContentType;
+    use rocket::local::blocking::Client;
     use rstest::*;
 
+    /// Count `input` with the elf preset, feeding it `chunk_size` bytes at a time.
+    fn count_elves(input: &str, chunk_size: usize) -> ElfCounter {
+        let mut counter = ElfCounter::streaming();
+
+        for chunk in input.as_bytes().chunks(chunk_size) {
+            counter.feed(chunk);
+        }
+
+        ElfCounter::from_counts(&counter.counts)
+    }
+
     #[test]
     fn test_elf_on_a_shelf() {
         let input = r#"The mischievous elf peeked out from behind the toy workshop,
                              and another elf joined in the festive dance.
                              Look, there is also an elf on that shelf!"#;
         let expected_elf_count = 4;
-        let Json(result) = elf_on_a_shelf(input);
+        let result = count_elves(input, StreamingCounter::CHUNK_SIZE);
 
         assert_eq!(result.elf, expected_elf_count);
     }
@@ -75,10 +683,271 @@ mod tests_day_06 {
             "shelf with no elf on it": 1
         }"#
     )]
-    fn test_elf_on_a_shelf_bonus(#[case] input: &str, #[case] response_body: &str) {
+    fn test_elf_on_a_shelf_bonus(
+        #[case] input: &str,
+        #[case] response_body: &str,
+        #[values(1, 3, 7, StreamingCounter::CHUNK_SIZE)] chunk_size: usize,
+    ) {
         let expected: ElfCounter = serde_json::from_str(response_body).unwrap();
-        let Json(result) = elf_on_a_shelf(input);
+        let result = count_elves(input, chunk_size);
 
         assert_eq!(result, expected);
     }
+
+    fn options(overlapping: bool, case_insensitive: bool, whole_word: bool) -> MatchOptions {
+        MatchOptions {
+            overlapping,
+            case_insensitive,
+            whole_word,
+            normalize: false,
+        }
+    }
+
+    #[rstest]
+    #[case(options(true, false, false), vec![2, 1, 1])]
+    #[case(options(false, false, false), vec![0, 0, 1])]
+    #[case(options(true, true, false), vec![4, 2, 1])]
+    #[case(options(true, true, true), vec![2, 2, 1])]
+    #[case(options(false, true, true), vec![1, 1, 1])]
+    fn test_phrase_matcher_options(#[case] options: MatchOptions, #[case] expected: Vec<usize>) {
+        let matcher = PhraseMatcher::new(&ElfCounter::PATTERNS, options).unwrap();
+        let text = "An ELF sat by the SHELF; the elf on a shelf";
+
+        assert_eq!(matcher.count(text), expected);
+    }
+
+    #[test]
+    fn test_phrase_matcher_normalize() {
+        let options = MatchOptions {
+            normalize: true,
+            case_insensitive: true,
+            ..Default::default()
+        };
+        let matcher = PhraseMatcher::new(&["elf", "café"], options).unwrap();
+        let text = "ＥＬＦ and cafe\u{301} and CAFÉ";
+        let matches = matcher.find(text);
+        let found: Vec<&str> = matches.iter().map(|m| &text[m.start..m.end]).collect();
+
+        assert_eq!(found, ["ＥＬＦ", "cafe\u{301}", "CAFÉ"]);
+        assert_eq!(matcher.count("ELF and cafe"), vec![1, 0]);
+    }
+
+    #[rstest]
+    #[case("\u{1100}\u{1161}\u{11A8}")]
+    #[case("\u{AC00}\u{11A8} and \u{1100}\u{1161}")]
+    #[case("e\u{301}\u{323}lf \u{FB01} \u{F73}\u{F73}")]
+    #[case("\u{B15}\u{B3E} and \u{B47}\u{B3E}")]
+    fn test_fold_matches_whole_text_nfkc(#[case] text: &str) {
+        let options = MatchOptions {
+            normalize: true,
+            ..Default::default()
+        };
+
+        assert_eq!(fold(text, options).text, text.nfkc().collect::<String>());
+    }
+
+    #[test]
+    fn test_phrase_matcher_normalize_across_runs() {
+        let options = MatchOptions {
+            normalize: true,
+            ..Default::default()
+        };
+        let matcher = PhraseMatcher::new(&["\u{AC01}"], options).unwrap();
+        let text = "\u{1100}\u{1161}\u{11A8}, \u{AC00}\u{11A8} and \u{AC01}";
+        let matches = matcher.find(text);
+        let found: Vec<&str> = matches.iter().map(|m| &text[m.start..m.end]).collect();
+
+        assert_eq!(
+            found,
+            ["\u{1100}\u{1161}\u{11A8}", "\u{AC00}\u{11A8}", "\u{AC01}"]
+        );
+    }
+
+    #[test]
+    fn test_phrase_matcher_dedupes_patterns() {
+        let matcher =
+            PhraseMatcher::new(&["elf", "shelf", "elf"], MatchOptions::default()).unwrap();
+
+        assert_eq!(matcher.patterns, ["elf", "shelf"]);
+        assert_eq!(matcher.count("elf on a shelf"), vec![2, 1]);
+    }
+
+    #[rstest]
+    #[case(&[], "At least one pattern is required")]
+    #[case(&["elf", ""], "Patterns must not be empty")]
+    fn test_count_phrases_error(#[case] patterns: &[&str], #[case] message: &str) {
+        let (status, err) =
+            count_phrases(patterns.to_vec(), MatchOptions::default(), "elf").unwrap_err();
+
+        assert_eq!(status, Status::BadRequest);
+        assert_eq!(err, message);
+    }
+
+    #[test]
+    fn test_count_phrases() {
+        let Json(result) = count_phrases(
+            vec!["shelf", "elf"],
+            MatchOptions::default(),
+            "there is an elf on a shelf",
+        )
+        .unwrap();
+
+        assert_eq!(
+            result.counts,
+            IndexMap::from([(String::from("shelf"), 1), (String::from("elf"), 2)])
+        );
+        assert_eq!(result.total, 3);
+    }
+
+    #[test]
+    fn test_match_positions() {
+        let text = "an elf\nthe élan of an elf on a shelf";
+        let Json(positions) =
+            match_positions(vec!["elf", "shelf"], MatchOptions::default(), text).unwrap();
+        let positions: Vec<_> = (positions.iter())
+            .map(|p| (p.pattern.as_str(), p.offset, p.end, p.line, p.column))
+            .collect();
+
+        assert_eq!(
+            positions,
+            [
+                ("elf", 3, 6, 1, 4),
+                ("elf", 23, 26, 2, 16),
+                ("shelf", 32, 37, 2, 25),
+                ("elf", 34, 37, 2, 27),
+            ]
+        );
+    }
+
+    #[rstest]
+    #[case("a shelf", &[("a ", None), ("shelf", Some("bare-shelf"))])]
+    #[case(
+        "an elf on a shelf on a shelf, a shelf",
+        &[
+            ("an ", None),
+            ("elf on a shelf on a shelf", Some("elf-on-a-shelf")),
+            (", a ", None),
+            ("shelf", Some("bare-shelf")),
+        ]
+    )]
+    #[case(
+        "shelf on a shelf",
+        &[("sh", None), ("elf on a shelf", Some("elf-on-a-shelf"))]
+    )]
+    #[case("no elves here", &[("no elves here", None)])]
+    #[case("", &[])]
+    fn test_highlight(#[case] text: &str, #[case] expected: &[(&str, Option<&str>)]) {
+        let matcher = PhraseMatcher::new(&ElfCounter::PATTERNS, MatchOptions::default()).unwrap();
+        let matches = matcher.find(text);
+        let segments: Vec<_> = (highlight(text, &matches).into_iter())
+            .map(|segment| (segment.text, segment.class))
+            .collect();
+
+        assert_eq!(segments, expected);
+    }
+
+    #[test]
+    fn test_phrase_matcher_streaming() {
+        let matcher = PhraseMatcher::new(&["elf", "shelf"], MatchOptions::default()).unwrap();
+        let mut counter = matcher.streaming().unwrap();
+
+        counter.feed(b"elf on a sh");
+        counter.feed(b"elf");
+
+        assert_eq!(counter.counts, matcher.count("elf on a shelf"));
+
+        let options = MatchOptions {
+            case_insensitive: true,
+            ..Default::default()
+        };
+
+        assert!(PhraseMatcher::new(&["elf"], options)
+            .unwrap()
+            .streaming()
+            .is_none());
+    }
+
+    /// A client whose document uploads are capped at `limit`.
+    fn documents_client(limit: &str) -> Client {
+        let figment = rocket::Config::figment().merge(("limits.elf-documents", limit));
+
+        Client::tracked(rocket::custom(figment).mount("/", rocket::routes![count_documents]))
+            .unwrap()
+    }
+
+    fn multipart(documents: &[(&str, &str)]) -> String {
+        let mut body = String::new();
+
+        for (name, text) in documents {
+            body.push_str(&format!(
+                "--elves\r\nContent-Disposition: form-data; name=\"document\"; \
+                filename=\"{name}\"\r\nContent-Type: text/plain\r\n\r\n{text}\r\n"
+            ));
+        }
+
+        body + "--elves--\r\n"
+    }
+
+    #[test]
+    fn test_count_documents() {
+        let client = documents_client("1KiB");
+        let content_type =
+            ContentType::new("multipart", "form-data").with_params(("boundary", "elves"));
+        let body = multipart(&[("a.txt", "an elf on a shelf"), ("b.txt", "a shelf")]);
+        let response = client
+            .post("/documents")
+            .header(content_type)
+            .body(body)
+            .dispatch();
+
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(
+            response.into_json::<serde_json::Value>().unwrap(),
+            serde_json::json!({
+                "documents": [
+                    {"name": "a.txt", "elf": 2, "elf on a shelf": 1, "shelf with no elf on it": 0},
+                    {"name": "b.txt", "elf": 1, "elf on a shelf": 0, "shelf with no elf on it": 1}
+                ],
+                "total": {"elf": 3, "elf on a shelf": 1, "shelf with no elf on it": 1}
+            })
+        );
+    }
+
+    #[rstest]
+    #[case(ContentType::new("multipart", "form-data").with_params(("boundary", "elves")), Status::PayloadTooLarge)]
+    #[case(ContentType::Plain, Status::UnsupportedMediaType)]
+    fn test_count_documents_error(#[case] content_type: ContentType, #[case] status: Status) {
+        let client = documents_client("64B");
+        let body = multipart(&[("a.txt", &"elf ".repeat(100))]);
+        let response = client
+            .post("/documents")
+            .header(content_type)
+            .body(body)
+            .dispatch();
+
+        assert_eq!(response.status(), status);
+    }
+
+    #[rocket::async_test]
+    async fn test_streaming_counter_read() {
+        let input = "elf on a shelf ".repeat(10_000);
+        let counts = ElfCounter::streaming()
+            .read(input.as_bytes(), input.len() as u64)
+            .await
+            .unwrap();
+        let matcher = PhraseMatcher::new(&ElfCounter::PATTERNS, MatchOptions::default()).unwrap();
+
+        assert_eq!(counts, matcher.count(&input));
+        assert_eq!(counts, [20_000, 10_000, 10_000]);
+    }
+
+    #[rocket::async_test]
+    async fn test_streaming_counter_limit() {
+        let (status, _) = ElfCounter::streaming()
+            .read("elf on a shelf".as_bytes(), 13)
+            .await
+            .unwrap_err();
+
+        assert_eq!(status, Status::PayloadTooLarge);
+    }
 }
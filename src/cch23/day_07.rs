@@ -1,18 +1,33 @@
+use std::borrow::Cow;
+use std::io::{Read, Write};
+use std::iter;
+
 use base64::engine::{general_purpose::URL_SAFE, Engine};
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
 use indexmap::map::IndexMap;
-use rocket::get;
-use rocket::http::{CookieJar, Status};
+use rocket::http::{Cookie, CookieJar, Status};
 use rocket::serde::{json::Json, Deserialize, Serialize};
+use rocket::{get, post};
 use serde::de::DeserializeOwned;
 
 #[derive(Debug, Serialize)]
 struct RecipeError {
-    error: &'static str,
+    error: Cow<'static, str>,
 }
 
 impl RecipeError {
     fn new(error: &'static str) -> RecipeError {
-        RecipeError { error }
+        RecipeError {
+            error: Cow::Borrowed(error),
+        }
+    }
+
+    fn owned(error: String) -> RecipeError {
+        RecipeError {
+            error: Cow::Owned(error),
+        }
     }
 
     #[allow(clippy::wrong_self_convention)]
@@ -21,25 +36,125 @@ impl RecipeError {
     }
 }
 
-const MAX_COOKIE_SIZE: usize = 4096;
+/// Upper bound on the size of a recipe, before and after decompression.
+const MAX_RECIPE_SIZE: usize = 64 * 1024;
+
+/// Size of each `recipe.N` cookie, leaving room for the base64 expansion of private cookies.
+const COOKIE_CHUNK_SIZE: usize = 2048;
 
-fn decode_cookie_recipe<T: DeserializeOwned>(cookie_b64: &str) -> Result<T, RecipeError> {
-    let mut bytes: [u8; MAX_COOKIE_SIZE] = [0; MAX_COOKIE_SIZE];
-    let size = URL_SAFE
-        .decode_slice(cookie_b64.as_bytes(), &mut bytes)
+/// Marks a payload holding deflate-compressed JSON.
+const COMPRESSED_PREFIX: char = '.';
+
+/// Decode a recipe payload, the base64 of its JSON or, after a `.`, of its deflated JSON.
+fn decode_cookie_recipe<T: DeserializeOwned>(payload: &str) -> Result<T, RecipeError> {
+    let too_large = || RecipeError::new("Recipe is too large, no cookies for Santa!");
+    let (compressed, recipe_b64) = match payload.strip_prefix(COMPRESSED_PREFIX) {
+        Some(recipe_b64) => (true, recipe_b64),
+        None => (false, payload),
+    };
+
+    if recipe_b64.len() > MAX_RECIPE_SIZE.div_ceil(3) * 4 {
+        return Err(too_large());
+    }
+
+    let bytes = URL_SAFE
+        .decode(recipe_b64)
         .map_err(|_| RecipeError::new("Recipe decoding failed, no cookies for Santa!"))?;
-    let value = serde_json::from_slice(&bytes[..size])
+    let json = if compressed {
+        let mut json = Vec::new();
+        let _size = DeflateDecoder::new(bytes.as_slice())
+            .take(MAX_RECIPE_SIZE as u64 + 1)
+            .read_to_end(&mut json)
+            .map_err(|_| RecipeError::new("Recipe decompression failed, no cookies for Santa!"))?;
+
+        json
+    } else {
+        bytes
+    };
+
+    if json.len() > MAX_RECIPE_SIZE {
+        return Err(too_large());
+    }
+
+    let value = serde_json::from_slice(&json)
         .map_err(|_| RecipeError::new("Recipe deserialization failed, no cookies for Santa!"))?;
 
     Ok(value)
 }
 
+fn encode_cookie_recipe<T: Serialize>(recipe: &T, compress: bool) -> Result<String, RecipeError> {
+    let failed = || RecipeError::new("Recipe serialization failed, no cookies for Santa!");
+    let json = serde_json::to_vec(recipe).map_err(|_| failed())?;
+
+    if json.len() > MAX_RECIPE_SIZE {
+        return Err(RecipeError::new(
+            "Recipe is too large, no cookies for Santa!",
+        ));
+    }
+
+    if compress {
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&json).map_err(|_| failed())?;
+        let deflated = encoder.finish().map_err(|_| failed())?;
+
+        Ok(format!("{COMPRESSED_PREFIX}{}", URL_SAFE.encode(deflated)))
+    } else {
+        Ok(URL_SAFE.encode(json))
+    }
+}
+
+/// Split a payload into the values of the `recipe.0`, `recipe.1`, … cookies.
+fn split_cookie_recipe(payload: &str) -> Vec<&str> {
+    (payload.as_bytes().chunks(COOKIE_CHUNK_SIZE))
+        .map(|chunk| std::str::from_utf8(chunk).expect("payloads are ASCII"))
+        .collect()
+}
+
+/// Prefix of the private cookie names, so that a private cookie is never read as a plain one.
+const PRIVATE_COOKIE_PREFIX: &str = "private.";
+
+/// The value of the cookie `name`, preferring its private counterpart over a plain cookie.
+///
+/// A private cookie that fails to decrypt was tampered with, or encrypted with another secret key,
+/// so it is rejected instead of falling back to anything else.
+fn cookie_value(cookies: &CookieJar<'_>, name: &str) -> Result<Option<String>, RecipeError> {
+    let private_name = format!("{PRIVATE_COOKIE_PREFIX}{name}");
+
+    if cookies.get(&private_name).is_none() {
+        return Ok(cookies.get(name).map(|cookie| cookie.value().to_owned()));
+    }
+
+    match cookies.get_private(&private_name) {
+        Some(cookie) => Ok(Some(cookie.value().to_owned())),
+        None => Err(RecipeError::new(
+            "Private recipe cookie is invalid or was tampered with, no cookies for Santa!",
+        )),
+    }
+}
+
+/// The recipe payload, from the `recipe` cookie or joined from `recipe.0`, `recipe.1`, …
+fn recipe_cookie(cookies: &CookieJar<'_>) -> Result<Option<String>, RecipeError> {
+    if let Some(recipe) = cookie_value(cookies, "recipe")? {
+        return Ok(Some(recipe));
+    }
+
+    let mut parts = Vec::new();
+
+    while let Some(part) = cookie_value(cookies, &format!("recipe.{}", parts.len()))? {
+        parts.push(part);
+    }
+
+    Ok((!parts.is_empty()).then(|| parts.concat()))
+}
+
 type JsonResult<T, E> = Result<Json<T>, (Status, Json<E>)>;
 
 #[get("/decode")]
 fn decode(cookies: &CookieJar<'_>) -> JsonResult<serde_json::Value, RecipeError> {
-    if let Some(recipe) = cookies.get("recipe").map(|cookie| cookie.value()) {
-        let recipe = decode_cookie_recipe(recipe)
+    let recipe = recipe_cookie(cookies).map_err(|err| err.as_response(Status::BadRequest))?;
+
+    if let Some(recipe) = recipe {
+        let recipe = decode_cookie_recipe(&recipe)
             .map_err(|err| err.as_response(Status::UnprocessableEntity))?;
 
         Ok(Json(recipe))
@@ -50,58 +165,825 @@ fn decode(cookies: &CookieJar<'_>) -> JsonResult<serde_json::Value, RecipeError>
 
 type IngredientUnit = u64;
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+/// A unit for an ingredient quantity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Unit {
+    G,
+    Kg,
+    Oz,
+    Lb,
+    Ml,
+    #[serde(alias = "cup")]
+    Cups,
+    Tsp,
+    Tbsp,
+    #[serde(alias = "piece")]
+    Pieces,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Dimension {
+    Mass,
+    Volume,
+    Count,
+}
+
+/// Nanograms in a gram, and femtolitres in a millilitre.
+const NANOGRAMS_PER_GRAM: f64 = 1e9;
+const FEMTOLITRES_PER_MILLILITRE: f64 = 1e12;
+
+impl Unit {
+    fn name(self) -> &'static str {
+        match self {
+            Unit::G => "g",
+            Unit::Kg => "kg",
+            Unit::Oz => "oz",
+            Unit::Lb => "lb",
+            Unit::Ml => "ml",
+            Unit::Cups => "cups",
+            Unit::Tsp => "tsp",
+            Unit::Tbsp => "tbsp",
+            Unit::Pieces => "pieces",
+        }
+    }
+
+    fn dimension(self) -> Dimension {
+        match self {
+            Unit::G | Unit::Kg | Unit::Oz | Unit::Lb => Dimension::Mass,
+            Unit::Ml | Unit::Cups | Unit::Tsp | Unit::Tbsp => Dimension::Volume,
+            Unit::Pieces => Dimension::Count,
+        }
+    }
+
+    /// The unit in nanograms, femtolitres or pieces, all of which are exact integers.
+    fn base(self) -> u128 {
+        match self {
+            Unit::G => 1_000_000_000,
+            Unit::Kg => 1_000_000_000_000,
+            Unit::Oz => 28_349_523_125,
+            Unit::Lb => 453_592_370_000,
+            Unit::Ml => 1_000_000_000_000,
+            Unit::Cups => 236_588_236_500_000,
+            Unit::Tsp => 4_928_921_593_750,
+            Unit::Tbsp => 14_786_764_781_250,
+            Unit::Pieces => 1,
+        }
+    }
+}
+
+/// Densities in grams per millilitre, used when the kitchen doesn't give one.
+const DEFAULT_DENSITIES: [(&str, f64); 10] = [
+    ("water", 1.0),
+    ("milk", 1.03),
+    ("flour", 0.53),
+    ("sugar", 0.85),
+    ("brown sugar", 0.93),
+    ("butter", 0.96),
+    ("honey", 1.42),
+    ("baking powder", 0.9),
+    ("cocoa powder", 0.42),
+    ("chocolate chips", 0.72),
+];
+
+/// Units per ingredient, on either side of the kitchen.
+///
+/// An ingredient with a unit on one side only uses it on both, and one with no unit at all is
+/// counted in pieces.
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+struct KitchenUnits {
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    recipe: IndexMap<String, Unit>,
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pantry: IndexMap<String, Unit>,
+}
+
+impl KitchenUnits {
+    fn is_empty(&self) -> bool {
+        self.recipe.is_empty() && self.pantry.is_empty()
+    }
+
+    fn of(&self, ingredient: &str) -> (Unit, Unit) {
+        match (self.recipe.get(ingredient), self.pantry.get(ingredient)) {
+            (Some(&recipe), Some(&pantry)) => (recipe, pantry),
+            (Some(&unit), None) | (None, Some(&unit)) => (unit, unit),
+            (None, None) => (Unit::Pieces, Unit::Pieces),
+        }
+    }
+}
+
+/// How a recipe quantity relates to a pantry quantity of the same ingredient.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Conversion {
+    /// Both units measure the same thing, as integer multiples of a common base.
+    Exact {
+        recipe_base: u128,
+        pantry_base: u128,
+    },
+    /// A volume and a mass, where one recipe unit is `factor` pantry units.
+    Density { factor: f64 },
+}
+
+/// Slack for the rounding of density conversions, so that exact amounts aren't rounded away.
+const DENSITY_TOLERANCE: f64 = 1e-9;
+
+impl Conversion {
+    fn new(
+        ingredient: &str,
+        recipe_unit: Unit,
+        pantry_unit: Unit,
+        densities: &IndexMap<String, f64>,
+    ) -> Result<Conversion, RecipeError> {
+        let density = || {
+            let density = (densities.get(ingredient).copied())
+                .or_else(|| {
+                    DEFAULT_DENSITIES
+                        .iter()
+                        .find(|(name, _)| *name == ingredient)
+                        .map(|&(_, density)| density)
+                })
+                .ok_or_else(|| {
+                    RecipeError::owned(format!(
+                        "No density for {ingredient}, no cookies for Santa!"
+                    ))
+                })?;
+
+            if density.is_finite() && density > 0.0 {
+                Ok(density)
+            } else {
+                Err(RecipeError::owned(format!(
+                    "Density of {ingredient} must be positive, no cookies for Santa!"
+                )))
+            }
+        };
+        let (recipe_base, pantry_base) = (recipe_unit.base(), pantry_unit.base());
+
+        match (recipe_unit.dimension(), pantry_unit.dimension()) {
+            (recipe, pantry) if recipe == pantry => {
+                let divisor = gcd(recipe_base, pantry_base);
+
+                Ok(Conversion::Exact {
+                    recipe_base: recipe_base / divisor,
+                    pantry_base: pantry_base / divisor,
+                })
+            }
+            (Dimension::Volume, Dimension::Mass) => {
+                let grams = recipe_base as f64 / FEMTOLITRES_PER_MILLILITRE * density()?;
+
+                Ok(Conversion::Density {
+                    factor: grams * NANOGRAMS_PER_GRAM / pantry_base as f64,
+                })
+            }
+            (Dimension::Mass, Dimension::Volume) => {
+                let millilitres = recipe_base as f64 / NANOGRAMS_PER_GRAM / density()?;
+
+                Ok(Conversion::Density {
+                    factor: millilitres * FEMTOLITRES_PER_MILLILITRE / pantry_base as f64,
+                })
+            }
+            _ => Err(RecipeError::owned(format!(
+                "Can't convert {} of {ingredient} to {}, no cookies for Santa!",
+                recipe_unit.name(),
+                pantry_unit.name(),
+            ))),
+        }
+    }
+
+    /// How many times `quantity` recipe units fit in `has` pantry units.
+    fn portions(self, has: IngredientUnit, quantity: IngredientUnit) -> IngredientUnit {
+        match self {
+            Conversion::Exact {
+                recipe_base,
+                pantry_base,
+            } => {
+                let portions = (has as u128 * pantry_base) / (quantity as u128 * recipe_base);
+                portions.try_into().unwrap_or(IngredientUnit::MAX)
+            }
+            Conversion::Density { factor } => {
+                let portions = has as f64 / (quantity as f64 * factor);
+                (portions + DENSITY_TOLERANCE).floor() as IngredientUnit
+            }
+        }
+    }
+
+    /// The pantry units taken by one portion of `quantity` recipe units, before rounding.
+    fn per_portion(self, quantity: IngredientUnit) -> f64 {
+        match self {
+            Conversion::Exact {
+                recipe_base,
+                pantry_base,
+            } => quantity as f64 * recipe_base as f64 / pantry_base as f64,
+            Conversion::Density { factor } => quantity as f64 * factor,
+        }
+    }
+
+    /// The pantry units needed for `portions` times `quantity` recipe units, rounded up, or
+    /// `None` when that is more than an [`IngredientUnit`] holds.
+    fn needed(self, portions: IngredientUnit, quantity: IngredientUnit) -> Option<IngredientUnit> {
+        match self {
+            Conversion::Exact {
+                recipe_base,
+                pantry_base,
+            } => (portions as u128 * quantity as u128)
+                .checked_mul(recipe_base)
+                .and_then(|needed| needed.div_ceil(pantry_base).try_into().ok()),
+            Conversion::Density { factor } => {
+                let needed =
+                    (portions as f64 * quantity as f64 * factor - DENSITY_TOLERANCE).ceil();
+                // `IngredientUnit::MAX as f64` rounds up to 2^64, which no longer fits.
+                (needed < IngredientUnit::MAX as f64).then_some(needed.max(0.0) as IngredientUnit)
+            }
+        }
+    }
+}
+
+fn gcd(mut a: u128, mut b: u128) -> u128 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+
+    a
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
 struct Kitchen {
     recipe: IndexMap<String, IngredientUnit>,
     pantry: IndexMap<String, IngredientUnit>,
+    #[serde(default, skip_serializing_if = "KitchenUnits::is_empty")]
+    units: KitchenUnits,
+    /// Grams per millilitre, taking precedence over [`DEFAULT_DENSITIES`].
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    densities: IndexMap<String, f64>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, PartialEq)]
 struct BakeCookies {
     cookies: IngredientUnit,
     pantry: IndexMap<String, IngredientUnit>,
+    #[serde(skip_serializing_if = "IndexMap::is_empty")]
+    units: IndexMap<String, Unit>,
+}
+
+impl Kitchen {
+    /// The needed ingredients of `recipe`, with their quantity and conversion to pantry units.
+    fn needs<'a>(
+        recipe: &'a IndexMap<String, IngredientUnit>,
+        units: &KitchenUnits,
+        densities: &IndexMap<String, f64>,
+    ) -> Result<Vec<(&'a String, IngredientUnit, Conversion)>, RecipeError> {
+        (recipe.iter())
+            .filter(|(_, &quantity)| quantity > 0)
+            .map(|(ingredient, &quantity)| {
+                let (recipe_unit, pantry_unit) = units.of(ingredient);
+                let conversion = Conversion::new(ingredient, recipe_unit, pantry_unit, densities)?;
+
+                Ok((ingredient, quantity, conversion))
+            })
+            .collect()
+    }
+
+    /// Bake as many cookies as the pantry allows.
+    ///
+    /// Ingredients with a zero quantity in the recipe are not needed, so a recipe that needs
+    /// nothing bakes no cookies. Recipe quantities are converted to the pantry's units, and the
+    /// pantry keeps its order and units, only the needed ingredients shrink.
+    fn bake(self) -> Result<BakeCookies, RecipeError> {
+        let Kitchen {
+            recipe,
+            mut pantry,
+            units,
+            densities,
+        } = self;
+        let needed = Kitchen::needs(&recipe, &units, &densities)?;
+        let cookies = (needed.iter())
+            .map(|&(ingredient, quantity, conversion)| {
+                (pantry.get(ingredient)).map_or(0, |&has| conversion.portions(has, quantity))
+            })
+            .min()
+            .unwrap_or(0);
+
+        if cookies > 0 {
+            for (ingredient, quantity, conversion) in needed {
+                if let Some(has) = pantry.get_mut(ingredient) {
+                    *has -= (conversion.needed(cookies, quantity))
+                        .map_or(*has, |needed| needed.min(*has));
+                }
+            }
+        }
+
+        Ok(BakeCookies {
+            cookies,
+            pantry,
+            units: units.pantry,
+        })
+    }
+
+    /// What to buy to bake exactly `cookies` cookies, and the pantry left after baking them.
+    ///
+    /// Shortfalls are in pantry units. Ingredients with a package size are bought in whole
+    /// packages, so the surplus stays in the pantry. Missing ingredients join the pantry.
+    fn shopping(
+        self,
+        cookies: IngredientUnit,
+        packages: &IndexMap<String, IngredientUnit>,
+    ) -> Result<ShoppingList, RecipeError> {
+        let Kitchen {
+            recipe,
+            mut pantry,
+            units,
+            densities,
+        } = self;
+        let mut shortfall = IndexMap::new();
+        let mut buy = IndexMap::new();
+
+        for (ingredient, quantity, conversion) in Kitchen::needs(&recipe, &units, &densities)? {
+            let too_much = || {
+                RecipeError::owned(format!(
+                    "Too much {ingredient} to shop for, no cookies for Santa!"
+                ))
+            };
+            let needed = conversion.needed(cookies, quantity).ok_or_else(too_much)?;
+            let has = pantry.entry(ingredient.clone()).or_default();
+            let missing = needed.saturating_sub(*has);
+            let bought = match packages.get(ingredient) {
+                Some(0) => {
+                    return Err(RecipeError::owned(format!(
+                        "Package size of {ingredient} must be positive, no cookies for Santa!"
+                    )))
+                }
+                Some(&size) => {
+                    let count = missing.div_ceil(size);
+
+                    buy.insert(ingredient.clone(), count);
+                    count.checked_mul(size).ok_or_else(too_much)?
+                }
+                None => missing,
+            };
+
+            let stocked = has.checked_add(bought).ok_or_else(too_much)?;
+
+            *has = stocked - needed.min(stocked);
+            shortfall.insert(ingredient.clone(), missing);
+        }
+
+        Ok(ShoppingList {
+            shortfall,
+            packages: buy,
+            baked: BakeCookies {
+                cookies,
+                pantry,
+                units: units.pantry,
+            },
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ShoppingRequest {
+    #[serde(flatten)]
+    kitchen: Kitchen,
+    cookies: IngredientUnit,
+    /// Package sizes in pantry units, for the ingredients that can't be bought loose.
+    #[serde(default)]
+    packages: IndexMap<String, IngredientUnit>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+struct ShoppingList {
+    shortfall: IndexMap<String, IngredientUnit>,
+    /// Packages to buy of each ingredient with a package size.
+    #[serde(skip_serializing_if = "IndexMap::is_empty")]
+    packages: IndexMap<String, IngredientUnit>,
+    #[serde(flatten)]
+    baked: BakeCookies,
+}
+
+/// Lists what is missing from the pantry to bake the requested number of cookies.
+#[post("/shopping", data = "<request>")]
+fn shopping(request: Json<ShoppingRequest>) -> JsonResult<ShoppingList, RecipeError> {
+    let ShoppingRequest {
+        kitchen,
+        cookies,
+        packages,
+    } = request.into_inner();
+
+    kitchen
+        .shopping(cookies, &packages)
+        .map(Json)
+        .map_err(|err| err.as_response(Status::UnprocessableEntity))
 }
 
 #[get("/bake")]
 fn bake(cookies: &CookieJar<'_>) -> JsonResult<BakeCookies, RecipeError> {
-    if let Some(recipe_b64) = cookies.get("recipe").map(|cookie| cookie.value()) {
-        let Kitchen { recipe, mut pantry } = decode_cookie_recipe(recipe_b64)
+    let recipe = recipe_cookie(cookies).map_err(|err| err.as_response(Status::BadRequest))?;
+
+    if let Some(recipe_b64) = recipe {
+        let kitchen: Kitchen = decode_cookie_recipe(&recipe_b64)
             .map_err(|err| err.as_response(Status::UnprocessableEntity))?;
-        let mut cookies: IngredientUnit = 0;
-        let mut pantry_update = pantry.clone();
+        let baked = kitchen
+            .bake()
+            .map_err(|err| err.as_response(Status::UnprocessableEntity))?;
+
+        Ok(Json(baked))
+    } else {
+        Err(RecipeError::new("No recipe cookie found").as_response(Status::BadRequest))
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct EncodedRecipe {
+    recipe: String,
+    private: bool,
+}
+
+/// Sets the `recipe` cookie for the posted kitchen, and returns its value.
+///
+/// With `private`, the cookie is encrypted and signed so that the pantry can't be tampered with,
+/// and its name gets the `private.` prefix. With `compress`, the JSON is deflated first. Recipes
+/// too large for one cookie are split across `recipe.0`, `recipe.1`, …
+#[post("/encode?<private>&<compress>", data = "<kitchen>")]
+fn encode(
+    kitchen: Json<Kitchen>,
+    private: Option<bool>,
+    compress: Option<bool>,
+    cookies: &CookieJar<'_>,
+) -> JsonResult<EncodedRecipe, RecipeError> {
+    let recipe = encode_cookie_recipe(&kitchen.0, compress.unwrap_or_default())
+        .map_err(|err| err.as_response(Status::UnprocessableEntity))?;
+    let private = private.unwrap_or_default();
+
+    // Clear every recipe cookie, so neither a stale part nor the other form shadows the new one.
+    for prefix in ["", PRIVATE_COOKIE_PREFIX] {
+        let parts = (0..)
+            .map(|index| format!("{prefix}recipe.{index}"))
+            .take_while(|name| cookies.get(name).is_some());
+        let stale: Vec<String> = iter::once(format!("{prefix}recipe"))
+            .filter(|name| cookies.get(name).is_some())
+            .chain(parts)
+            .collect();
+
+        for name in stale {
+            cookies.remove(name);
+        }
+    }
+
+    let prefix = if private { PRIVATE_COOKIE_PREFIX } else { "" };
+    let parts = split_cookie_recipe(&recipe);
+    let named_parts: Vec<(String, &str)> = match parts.as_slice() {
+        [part] => vec![(format!("{prefix}recipe"), part)],
+        parts => (parts.iter().enumerate())
+            .map(|(index, &part)| (format!("{prefix}recipe.{index}"), part))
+            .collect(),
+    };
+
+    for (name, part) in named_parts {
+        let cookie = Cookie::new(name, String::from(part));
+
+        if private {
+            cookies.add_private(cookie);
+        } else {
+            cookies.add(cookie);
+        }
+    }
+
+    Ok(Json(EncodedRecipe { recipe, private }))
+}
+
+fn default_weight() -> f64 {
+    1.0
+}
+
+#[derive(Debug, Deserialize)]
+struct PlannedRecipe {
+    recipe: IndexMap<String, IngredientUnit>,
+    #[serde(default)]
+    units: IndexMap<String, Unit>,
+    /// Value of one cookie, a much larger weight makes a recipe a priority.
+    #[serde(default = "default_weight")]
+    weight: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct PlanRequest {
+    recipes: IndexMap<String, PlannedRecipe>,
+    pantry: IndexMap<String, IngredientUnit>,
+    #[serde(default)]
+    units: IndexMap<String, Unit>,
+    #[serde(default)]
+    densities: IndexMap<String, f64>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+struct BakePlan {
+    plan: IndexMap<String, IngredientUnit>,
+    value: f64,
+    /// Whether the search finished within [`PLAN_NODE_BUDGET`], proving the plan optimal.
+    optimal: bool,
+    #[serde(flatten)]
+    baked: BakeCookies,
+}
+
+/// Upper bound on the nodes visited by [`plan_cookies`], after which the best plan so far is used.
+const PLAN_NODE_BUDGET: usize = 100_000;
+
+/// Most recipes planned at once, which also bounds the depth of the search.
+const MAX_PLAN_RECIPES: usize = 16;
+
+/// Most pantry ingredients, since every node of the search walks them all.
+const MAX_PLAN_INGREDIENTS: usize = 32;
+
+/// A recipe reduced to what it takes from each pantry ingredient per cookie.
+#[derive(Debug)]
+struct PlanRecipe {
+    weight: f64,
+    /// Pantry index, recipe quantity and conversion of each needed ingredient, or `None` when
+    /// the recipe can't be baked from this pantry.
+    needs: Option<Vec<(usize, IngredientUnit, Conversion)>>,
+}
+
+impl PlanRecipe {
+    fn max_cookies(&self, pantry: &[IngredientUnit]) -> IngredientUnit {
+        match &self.needs {
+            Some(needs) if self.weight > 0.0 => (needs.iter())
+                .map(|&(index, quantity, conversion)| conversion.portions(pantry[index], quantity))
+                .min()
+                .unwrap_or(0),
+            _ => 0,
+        }
+    }
+
+    /// Weight per cookie over the share of the pantry one cookie uses up, or zero when none fit.
+    fn density(&self, pantry: &[IngredientUnit]) -> f64 {
+        match &self.needs {
+            Some(needs) if self.max_cookies(pantry) > 0 => {
+                let share: f64 = (needs.iter())
+                    .map(|&(index, quantity, conversion)| {
+                        conversion.per_portion(quantity) / pantry[index] as f64
+                    })
+                    .sum();
+
+                self.weight / share
+            }
+            _ => 0.0,
+        }
+    }
+
+    fn take(&self, pantry: &mut [IngredientUnit], cookies: IngredientUnit) {
+        for &(index, quantity, conversion) in self.needs.iter().flatten() {
+            let has = pantry[index];
+            pantry[index] -=
+                (conversion.needed(cookies, quantity)).map_or(has, |needed| needed.min(has));
+        }
+    }
+}
+
+/// The value and counts of a plan that repeatedly bakes half of what fits of the densest recipe.
+///
+/// Densities are measured against what is left of the pantry, so that one recipe doesn't starve
+/// the others of an ingredient they share. Leftovers are then baked densest recipe first.
+fn greedy_plan(recipes: &[PlanRecipe], pantry: &[IngredientUnit]) -> (f64, Vec<IngredientUnit>) {
+    let mut rest = pantry.to_vec();
+    let mut plan = (0.0, vec![0; recipes.len()]);
+    let mut bake = |r: usize, cookies: IngredientUnit, rest: &mut [IngredientUnit]| {
+        recipes[r].take(rest, cookies);
+        plan.0 += recipes[r].weight * cookies as f64;
+        plan.1[r] += cookies;
+    };
+
+    // Each recipe is picked at most once per halving of what fits of it.
+    for _ in 0..recipes.len() * IngredientUnit::BITS as usize {
+        let densest = (0..recipes.len())
+            .map(|r| (r, recipes[r].density(&rest)))
+            .filter(|&(_, density)| density > 0.0)
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        let Some((r, _)) = densest else {
+            break;
+        };
+
+        bake(r, recipes[r].max_cookies(&rest).div_ceil(2), &mut rest);
+    }
+
+    let mut order: Vec<usize> = (0..recipes.len()).collect();
+
+    order.sort_by(|&a, &b| {
+        recipes[b]
+            .density(&rest)
+            .total_cmp(&recipes[a].density(&rest))
+    });
+
+    for r in order {
+        bake(r, recipes[r].max_cookies(&rest), &mut rest);
+    }
+
+    plan
+}
+
+/// Branch and bound over the cookie count of each recipe, densest recipes first.
+///
+/// The search starts from [`greedy_plan`], so that running out of budget still leaves a good
+/// plan. Returns the counts in the order of `recipes`, and whether the search completed.
+fn plan_cookies(recipes: &[PlanRecipe], pantry: &[IngredientUnit]) -> (Vec<IngredientUnit>, bool) {
+    struct Search<'a> {
+        recipes: &'a [PlanRecipe],
+        order: Vec<usize>,
+        counts: Vec<IngredientUnit>,
+        best: (f64, Vec<IngredientUnit>),
+        nodes: usize,
+    }
+
+    impl<'a> Search<'a> {
+        /// Upper bounds on the value the recipes from `depth` on can add, and those after it.
+        ///
+        /// Each recipe alone bounds its own cookies, and an ingredient that every remaining
+        /// recipe needs bounds them all together.
+        fn bounds(&self, depth: usize, pantry: &[IngredientUnit]) -> (f64, f64) {
+            let mut alone = 0.0;
+            // The best weight per pantry unit of each ingredient, and how many recipes need it.
+            let mut rates = vec![(0.0_f64, 0); pantry.len()];
+            let bound = |alone: f64, rates: &[(f64, usize)], recipes: usize| {
+                let shared = (rates.iter().zip(pantry))
+                    .filter(|((_, needed_by), _)| *needed_by == recipes)
+                    .map(|((rate, _), &has)| rate * has as f64)
+                    .fold(f64::INFINITY, f64::min);
 
-        'baking_counter: loop {
-            'cookie: for (ingredient, &quantity) in &recipe {
-                if quantity == 0 {
-                    continue 'cookie;
+                alone.min(shared)
+            };
+            let mut rest_bound = 0.0;
+
+            for (i, &r) in self.order.iter().enumerate().skip(depth).rev() {
+                if i == depth {
+                    rest_bound = bound(alone, &rates, self.order.len() - depth - 1);
                 }
 
-                match pantry.get(ingredient) {
-                    Some(&pantry_has) if quantity <= pantry_has => {
-                        pantry_update.insert(ingredient.to_owned(), pantry_has - quantity);
-                    }
-                    // Pantry does not have enough
-                    Some(_) | None => break 'baking_counter,
+                let recipe = &self.recipes[r];
+                alone += recipe.weight * recipe.max_cookies(pantry) as f64;
+
+                for &(index, quantity, conversion) in recipe.needs.iter().flatten() {
+                    let (rate, needed_by) = &mut rates[index];
+                    *rate = rate.max(recipe.weight / conversion.per_portion(quantity));
+                    *needed_by += 1;
                 }
             }
 
-            cookies += 1;
-            pantry.clone_from(&pantry_update);
+            (bound(alone, &rates, self.order.len() - depth), rest_bound)
         }
 
-        Ok(Json(BakeCookies { cookies, pantry }))
-    } else {
-        Err(RecipeError::new("No recipe cookie found").as_response(Status::BadRequest))
+        fn visit(&mut self, depth: usize, pantry: &[IngredientUnit], value: f64) -> bool {
+            if self.nodes >= PLAN_NODE_BUDGET {
+                return false;
+            }
+
+            self.nodes += 1;
+
+            let recipe = &self.recipes[self.order[depth]];
+            let max_cookies = recipe.max_cookies(pantry);
+
+            if depth + 1 == self.order.len() {
+                let value = value + recipe.weight * max_cookies as f64;
+                self.counts[self.order[depth]] = max_cookies;
+
+                if value > self.best.0 {
+                    self.best = (value, self.counts.clone());
+                }
+
+                return true;
+            }
+
+            // Fewer cookies here leave at most the whole pantry to the rest, and no count beats
+            // the bound of the whole subtree.
+            let (subtree_bound, rest_bound) = self.bounds(depth, pantry);
+
+            for cookies in (0..=max_cookies).rev() {
+                let bound = (recipe.weight * cookies as f64 + rest_bound).min(subtree_bound);
+
+                if value + bound <= self.best.0 {
+                    break;
+                }
+
+                let mut rest = pantry.to_vec();
+                recipe.take(&mut rest, cookies);
+                self.counts[self.order[depth]] = cookies;
+
+                if !self.visit(depth + 1, &rest, value + recipe.weight * cookies as f64) {
+                    return false;
+                }
+            }
+
+            true
+        }
     }
+
+    let mut order: Vec<usize> = (0..recipes.len()).collect();
+    let densities: Vec<f64> = (recipes.iter())
+        .map(|recipe| recipe.density(pantry))
+        .collect();
+
+    order.sort_by(|&a, &b| densities[b].total_cmp(&densities[a]));
+
+    if order.is_empty() {
+        return (Vec::new(), true);
+    }
+
+    let mut search = Search {
+        recipes,
+        order,
+        counts: vec![0; recipes.len()],
+        best: greedy_plan(recipes, pantry),
+        nodes: 0,
+    };
+    let complete = search.visit(0, pantry, 0.0);
+
+    (search.best.1, complete)
+}
+
+/// Plans how many cookies of each recipe to bake from one pantry, maximising their total weight.
+#[post("/plan", data = "<request>")]
+fn plan(request: Json<PlanRequest>) -> JsonResult<BakePlan, RecipeError> {
+    let PlanRequest {
+        recipes,
+        pantry,
+        units,
+        densities,
+    } = request.into_inner();
+    let to_response = |err: RecipeError| err.as_response(Status::UnprocessableEntity);
+
+    if recipes.len() > MAX_PLAN_RECIPES {
+        return Err(to_response(RecipeError::owned(format!(
+            "At most {MAX_PLAN_RECIPES} recipes can be planned, no cookies for Santa!"
+        ))));
+    }
+
+    if pantry.len() > MAX_PLAN_INGREDIENTS {
+        return Err(to_response(RecipeError::owned(format!(
+            "At most {MAX_PLAN_INGREDIENTS} pantry ingredients can be planned, no cookies for Santa!"
+        ))));
+    }
+
+    let planned = (recipes.iter())
+        .map(|(name, planned)| {
+            if !planned.weight.is_finite() || planned.weight < 0.0 {
+                return Err(RecipeError::owned(format!(
+                    "Weight of {name} must not be negative, no cookies for Santa!"
+                )));
+            }
+
+            let kitchen_units = KitchenUnits {
+                recipe: planned.units.clone(),
+                pantry: units.clone(),
+            };
+            let needs = (planned.recipe.iter())
+                .filter(|(_, &quantity)| quantity > 0)
+                .map(|(ingredient, &quantity)| {
+                    let (recipe_unit, pantry_unit) = kitchen_units.of(ingredient);
+                    let conversion =
+                        Conversion::new(ingredient, recipe_unit, pantry_unit, &densities)?;
+
+                    Ok(pantry
+                        .get_index_of(ingredient)
+                        .map(|index| (index, quantity, conversion)))
+                })
+                .collect::<Result<Option<Vec<_>>, RecipeError>>()?;
+
+            Ok(PlanRecipe {
+                weight: planned.weight,
+                needs: needs.filter(|needs| !needs.is_empty()),
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(to_response)?;
+    let mut left: Vec<IngredientUnit> = pantry.values().copied().collect();
+    let (counts, optimal) = plan_cookies(&planned, &left);
+
+    for (recipe, &cookies) in planned.iter().zip(&counts) {
+        recipe.take(&mut left, cookies);
+    }
+
+    let value = (planned.iter().zip(&counts))
+        .map(|(recipe, &cookies)| recipe.weight * cookies as f64)
+        .sum();
+
+    Ok(Json(BakePlan {
+        value,
+        optimal,
+        baked: BakeCookies {
+            cookies: counts
+                .iter()
+                .fold(0, |total, &cookies| total.saturating_add(cookies)),
+            pantry: pantry.into_keys().zip(left).collect(),
+            units,
+        },
+        plan: recipes.into_keys().zip(counts).collect(),
+    }))
 }
 
 pub fn routes() -> Vec<rocket::Route> {
-    rocket::routes![decode, bake]
+    rocket::routes![decode, bake, encode, plan, shopping]
 }
 
 #[cfg(test)]
 mod tests_day_07 {
     use super::*;
+    use rocket::local::blocking::Client;
     use rstest::*;
 
     #[rstest]
@@ -110,7 +992,7 @@ mod tests_day_07 {
     fn test_decode_cookie_value(#[case] cookie: &str, #[case] expected: serde_json::Value) {
         match decode_cookie_recipe::<serde_json::Value>(cookie) {
             Ok(value) => assert_eq!(value, expected),
-            Err(err) => assert!(false, "{}", err.error),
+            Err(err) => panic!("{}", err.error),
         }
     }
 
@@ -132,6 +1014,7 @@ mod tests_day_07 {
                 ("baking powder".to_owned(), 865),
                 ("chocolate chips".to_owned(), 457),
             ]),
+            ..Default::default()
         },
     )]
     #[case(
@@ -144,12 +1027,644 @@ mod tests_day_07 {
                 ("cobblestone".to_owned(), 64),
                 ("stick".to_owned(), 4),
             ]),
+            ..Default::default()
         },
     )]
     fn test_decode_cookie_kitchen(#[case] cookie: &str, #[case] expected: Kitchen) {
         match decode_cookie_recipe::<Kitchen>(cookie) {
             Ok(kitchen) => assert_eq!(kitchen, expected),
-            Err(err) => assert!(false, "{}", err.error),
+            Err(err) => panic!("{}", err.error),
+        }
+    }
+
+    #[test]
+    fn test_encode_cookie_recipe() {
+        let kitchen = Kitchen {
+            recipe: IndexMap::from([("flour".to_owned(), 95), ("sugar".to_owned(), 50)]),
+            pantry: IndexMap::from([("sugar".to_owned(), 507), ("flour".to_owned(), 385)]),
+            ..Default::default()
+        };
+        let cookie = encode_cookie_recipe(&kitchen, false).unwrap();
+
+        assert_eq!(decode_cookie_recipe::<Kitchen>(&cookie).unwrap(), kitchen);
+    }
+
+    fn large_kitchen(size: usize) -> Kitchen {
+        Kitchen {
+            recipe: (0..size).map(|i| (format!("ingredient {i}"), 1)).collect(),
+            pantry: (0..size)
+                .map(|i| (format!("ingredient {i}"), i as u64))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[rstest]
+    #[case(false)]
+    #[case(true)]
+    fn test_encode_cookie_recipe_large(#[case] compress: bool) {
+        let kitchen = large_kitchen(1000);
+        let payload = encode_cookie_recipe(&kitchen, compress).unwrap();
+        let parts = split_cookie_recipe(&payload);
+
+        assert_eq!(payload.starts_with(COMPRESSED_PREFIX), compress);
+        assert!(parts.len() > 1);
+        assert!(parts.iter().all(|part| part.len() <= COOKIE_CHUNK_SIZE));
+        assert_eq!(
+            decode_cookie_recipe::<Kitchen>(&parts.concat()).unwrap(),
+            kitchen
+        );
+    }
+
+    #[rstest]
+    fn test_encode_then_decode(
+        #[values(false, true)] private: bool,
+        #[values(false, true)] compress: bool,
+    ) {
+        let client = Client::tracked(rocket::build().mount("/", routes())).unwrap();
+        let kitchen = large_kitchen(1000);
+        let response = client
+            .post(format!("/encode?private={private}&compress={compress}"))
+            .json(&kitchen)
+            .dispatch();
+
+        assert_eq!(response.status(), Status::Ok);
+        assert!(response
+            .cookies()
+            .iter()
+            .all(|cookie| cookie.name().starts_with(PRIVATE_COOKIE_PREFIX) == private));
+
+        let response = client.get("/decode").dispatch();
+
+        assert_eq!(response.into_json::<Kitchen>().unwrap(), kitchen);
+    }
+
+    #[test]
+    fn test_decode_tampered_private_cookie() {
+        let client = Client::untracked(rocket::build().mount("/", routes())).unwrap();
+        let response = client
+            .get("/decode")
+            .cookie(Cookie::new(
+                "private.recipe",
+                "eyJwZWFudXQgYnV0dGVyIjo0MH0=",
+            ))
+            .cookie(Cookie::new("recipe", "eyJwZWFudXQgYnV0dGVyIjo0MH0="))
+            .dispatch();
+
+        assert_eq!(response.status(), Status::BadRequest);
+        assert_eq!(
+            response.into_json::<serde_json::Value>().unwrap(),
+            serde_json::json!({
+                "error": "Private recipe cookie is invalid or was tampered with, no cookies for Santa!"
+            })
+        );
+    }
+
+    #[rstest]
+    #[case(
+        "eyJwZWFudXQgYnV0dGVyIjo0MH0",
+        "Recipe decoding failed, no cookies for Santa!"
+    )]
+    #[case("not base64!", "Recipe decoding failed, no cookies for Santa!")]
+    #[case(
+        ".eyJwZWFudXQgYnV0dGVyIjo0MH0=",
+        "Recipe decompression failed, no cookies for Santa!"
+    )]
+    #[case("ew==", "Recipe deserialization failed, no cookies for Santa!")]
+    #[case(
+        "eyJwZWFudXQgYnV0dGVyIjo0MA==",
+        "Recipe deserialization failed, no cookies for Santa!"
+    )]
+    fn test_decode_cookie_recipe_error(#[case] payload: &str, #[case] message: &str) {
+        let err = decode_cookie_recipe::<serde_json::Value>(payload).unwrap_err();
+
+        assert_eq!(err.error, message);
+    }
+
+    #[test]
+    fn test_decode_cookie_recipe_too_large() {
+        let payload = URL_SAFE.encode(vec![b' '; MAX_RECIPE_SIZE + 1]);
+        let err = decode_cookie_recipe::<serde_json::Value>(&payload).unwrap_err();
+
+        assert_eq!(err.error, "Recipe is too large, no cookies for Santa!");
+
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder
+            .write_all(&vec![b' '; 10 * MAX_RECIPE_SIZE])
+            .unwrap();
+        let bomb = format!(
+            "{COMPRESSED_PREFIX}{}",
+            URL_SAFE.encode(encoder.finish().unwrap())
+        );
+        let err = decode_cookie_recipe::<serde_json::Value>(&bomb).unwrap_err();
+
+        assert_eq!(err.error, "Recipe is too large, no cookies for Santa!");
+    }
+
+    fn ingredients(items: &[(&str, IngredientUnit)]) -> IndexMap<String, IngredientUnit> {
+        (items.iter())
+            .map(|&(ingredient, quantity)| (ingredient.to_owned(), quantity))
+            .collect()
+    }
+
+    #[rstest]
+    #[case(
+        &[("flour", 95), ("sugar", 50), ("butter", 30), ("baking powder", 10), ("chocolate chips", 50)],
+        &[("flour", 385), ("sugar", 507), ("butter", 2122), ("baking powder", 865), ("chocolate chips", 457)],
+        4,
+        &[("flour", 5), ("sugar", 307), ("butter", 2002), ("baking powder", 825), ("chocolate chips", 257)],
+    )]
+    #[case(
+        &[("slime", 9)],
+        &[("cobblestone", 64), ("stick", 4)],
+        0,
+        &[("cobblestone", 64), ("stick", 4)],
+    )]
+    #[case(
+        &[("chicken", 1), ("egg", 0)],
+        &[("egg", 0), ("chicken", 5), ("salt", 3)],
+        5,
+        &[("egg", 0), ("chicken", 0), ("salt", 3)],
+    )]
+    #[case(&[("egg", 0)], &[("egg", 3)], 0, &[("egg", 3)])]
+    #[case(&[], &[("egg", 3)], 0, &[("egg", 3)])]
+    #[case(
+        &[("flour", 1)],
+        &[("sugar", u64::MAX), ("flour", u64::MAX)],
+        u64::MAX,
+        &[("sugar", u64::MAX), ("flour", 0)],
+    )]
+    #[case(
+        &[("flour", 3), ("sugar", u64::MAX)],
+        &[("flour", u64::MAX), ("sugar", u64::MAX)],
+        1,
+        &[("flour", u64::MAX - 3), ("sugar", 0)],
+    )]
+    #[case(
+        &[("flour", 2), ("sugar", 7)],
+        &[("flour", u64::MAX), ("sugar", u64::MAX)],
+        u64::MAX / 7,
+        &[("flour", u64::MAX - u64::MAX / 7 * 2), ("sugar", u64::MAX % 7)],
+    )]
+    fn test_bake(
+        #[case] recipe: &[(&str, IngredientUnit)],
+        #[case] pantry: &[(&str, IngredientUnit)],
+        #[case] cookies: IngredientUnit,
+        #[case] expected: &[(&str, IngredientUnit)],
+    ) {
+        let kitchen = Kitchen {
+            recipe: ingredients(recipe),
+            pantry: ingredients(pantry),
+            ..Default::default()
+        };
+        let baked = kitchen.bake().unwrap();
+
+        assert_eq!(baked.cookies, cookies);
+        assert!(baked.pantry.iter().eq(ingredients(expected).iter()));
+    }
+
+    #[rstest]
+    #[case(
+        serde_json::json!({
+            "recipe": {"flour": 1},
+            "pantry": {"flour": 2500},
+            "units": {"recipe": {"flour": "kg"}, "pantry": {"flour": "g"}},
+        }),
+        2,
+        serde_json::json!({"flour": 500}),
+    )]
+    #[case(
+        serde_json::json!({
+            "recipe": {"butter": 1, "sugar": 1},
+            "pantry": {"butter": 33, "sugar": 10},
+            "units": {"recipe": {"butter": "lb", "sugar": "tbsp"}, "pantry": {"butter": "oz", "sugar": "tsp"}},
+        }),
+        2,
+        serde_json::json!({"butter": 1, "sugar": 4}),
+    )]
+    #[case(
+        serde_json::json!({
+            "recipe": {"flour": 2, "egg": 1},
+            "pantry": {"flour": 1000, "egg": 12},
+            "units": {"recipe": {"flour": "cup"}, "pantry": {"flour": "g"}},
+        }),
+        3,
+        serde_json::json!({"flour": 247, "egg": 9}),
+    )]
+    #[case(
+        serde_json::json!({
+            "recipe": {"milk": 100},
+            "pantry": {"milk": 1000},
+            "units": {"recipe": {"milk": "g"}, "pantry": {"milk": "ml"}},
+        }),
+        10,
+        serde_json::json!({"milk": 29}),
+    )]
+    #[case(
+        serde_json::json!({
+            "recipe": {"honey": 10},
+            "pantry": {"honey": 100},
+            "units": {"recipe": {"honey": "ml"}, "pantry": {"honey": "g"}},
+            "densities": {"honey": 1.0},
+        }),
+        10,
+        serde_json::json!({"honey": 0}),
+    )]
+    #[case(
+        serde_json::json!({
+            "recipe": {"sugar": 150},
+            "pantry": {"sugar": 1000},
+            "units": {"recipe": {"sugar": "g"}},
+        }),
+        6,
+        serde_json::json!({"sugar": 100}),
+    )]
+    fn test_bake_units(
+        #[case] kitchen: serde_json::Value,
+        #[case] cookies: IngredientUnit,
+        #[case] pantry: serde_json::Value,
+    ) {
+        let kitchen: Kitchen = serde_json::from_value(kitchen).unwrap();
+        let units = kitchen.units.pantry.clone();
+        let baked = kitchen.bake().unwrap();
+
+        assert_eq!(baked.cookies, cookies);
+        assert_eq!(serde_json::to_value(&baked.pantry).unwrap(), pantry);
+        assert_eq!(baked.units, units);
+    }
+
+    #[rstest]
+    #[case(
+        serde_json::json!({"egg": "pieces"}),
+        serde_json::json!({"egg": "g"}),
+        serde_json::json!({}),
+        "Can't convert pieces of egg to g, no cookies for Santa!",
+    )]
+    #[case(
+        serde_json::json!({"glitter": "tsp"}),
+        serde_json::json!({"glitter": "g"}),
+        serde_json::json!({}),
+        "No density for glitter, no cookies for Santa!",
+    )]
+    #[case(
+        serde_json::json!({"flour": "cups"}),
+        serde_json::json!({"flour": "g"}),
+        serde_json::json!({"flour": 0.0}),
+        "Density of flour must be positive, no cookies for Santa!",
+    )]
+    fn test_bake_units_error(
+        #[case] recipe_units: serde_json::Value,
+        #[case] pantry_units: serde_json::Value,
+        #[case] densities: serde_json::Value,
+        #[case] message: &str,
+    ) {
+        let ingredient = recipe_units
+            .as_object()
+            .unwrap()
+            .keys()
+            .next()
+            .unwrap()
+            .clone();
+        let kitchen: Kitchen = serde_json::from_value(serde_json::json!({
+            "recipe": {&ingredient: 1},
+            "pantry": {&ingredient: 100},
+            "units": {"recipe": recipe_units, "pantry": pantry_units},
+            "densities": densities,
+        }))
+        .unwrap();
+        let err = kitchen.bake().unwrap_err();
+
+        assert_eq!(err.error, message);
+    }
+
+    #[test]
+    fn test_bake_units_huge() {
+        let kitchen = Kitchen {
+            recipe: ingredients(&[("flour", 1)]),
+            pantry: ingredients(&[("flour", u64::MAX)]),
+            units: KitchenUnits {
+                recipe: IndexMap::from([("flour".to_owned(), Unit::G)]),
+                pantry: IndexMap::from([("flour".to_owned(), Unit::Kg)]),
+            },
+            ..Default::default()
+        };
+        let baked = kitchen.bake().unwrap();
+
+        assert_eq!(baked.cookies, u64::MAX);
+        assert_eq!(baked.pantry["flour"], u64::MAX - u64::MAX / 1000 - 1);
+    }
+
+    fn plan_json(request: serde_json::Value) -> Result<BakePlan, String> {
+        match plan(Json(serde_json::from_value(request).unwrap())) {
+            Ok(Json(plan)) => Ok(plan),
+            Err((_, Json(err))) => Err(err.error.into_owned()),
         }
     }
+
+    #[rstest]
+    #[case(
+        serde_json::json!({
+            "cookie": {"recipe": {"flour": 2, "sugar": 1}},
+            "brownie": {"recipe": {"flour": 3, "chocolate": 2}},
+        }),
+        &[("flour", 12), ("sugar", 3), ("chocolate", 4)],
+        &[("cookie", 3), ("brownie", 2)],
+        5.0,
+        &[("flour", 0), ("sugar", 0), ("chocolate", 0)],
+    )]
+    #[case(
+        serde_json::json!({
+            "cookie": {"recipe": {"flour": 2}, "weight": 1.0},
+            "cake": {"recipe": {"flour": 5}, "weight": 3.0},
+        }),
+        &[("flour", 12)],
+        &[("cookie", 1), ("cake", 2)],
+        7.0,
+        &[("flour", 0)],
+    )]
+    #[case(
+        serde_json::json!({
+            "cookie": {"recipe": {"flour": 1}},
+            "cake": {"recipe": {"flour": 1, "egg": 1}, "weight": 0.0},
+            "pie": {"recipe": {"apple": 1}},
+        }),
+        &[("flour", 4), ("egg", 4)],
+        &[("cookie", 4), ("cake", 0), ("pie", 0)],
+        4.0,
+        &[("flour", 0), ("egg", 4)],
+    )]
+    fn test_plan(
+        #[case] recipes: serde_json::Value,
+        #[case] pantry: &[(&str, IngredientUnit)],
+        #[case] expected_plan: &[(&str, IngredientUnit)],
+        #[case] value: f64,
+        #[case] left: &[(&str, IngredientUnit)],
+    ) {
+        let plan = plan_json(serde_json::json!({
+            "recipes": recipes,
+            "pantry": ingredients(pantry),
+        }))
+        .unwrap();
+
+        assert_eq!(plan.plan, ingredients(expected_plan));
+        assert_eq!(plan.value, value);
+        assert!(plan.optimal);
+        assert_eq!(
+            plan.baked.cookies,
+            plan.plan.values().sum::<IngredientUnit>()
+        );
+        assert_eq!(plan.baked.pantry, ingredients(left));
+    }
+
+    #[test]
+    fn test_plan_units() {
+        let plan = plan_json(serde_json::json!({
+            "recipes": {
+                "cookie": {"recipe": {"flour": 500}, "units": {"flour": "g"}},
+                "bread": {"recipe": {"flour": 1}, "units": {"flour": "kg"}, "weight": 2.5},
+            },
+            "pantry": {"flour": 3},
+            "units": {"flour": "kg"},
+        }))
+        .unwrap();
+
+        assert_eq!(plan.plan, ingredients(&[("cookie", 0), ("bread", 3)]));
+        assert_eq!(plan.baked.pantry, ingredients(&[("flour", 0)]));
+    }
+
+    #[test]
+    fn test_plan_budget() {
+        let plan = plan_json(serde_json::json!({
+            "recipes": {
+                "cake": {"recipe": {"flour": 3}, "weight": 2.0},
+                "cookie": {"recipe": {"flour": 1}},
+            },
+            "pantry": {"flour": u64::MAX},
+        }))
+        .unwrap();
+
+        // Cookies give more weight per unit of flour, so the best plan is flour as cookies.
+        assert_eq!(plan.plan["cookie"], u64::MAX);
+        assert_eq!(plan.plan["cake"], 0);
+        assert_eq!(plan.baked.pantry["flour"], 0);
+    }
+
+    #[test]
+    fn test_plan_budget_shared_ingredients() {
+        let plan = plan_json(serde_json::json!({
+            "recipes": {
+                "a": {"recipe": {"flour": 3, "sugar": 1}, "weight": 3.0},
+                "b": {"recipe": {"flour": 1, "sugar": 3}, "weight": 3.0},
+                "c": {"recipe": {"flour": 2, "sugar": 2}, "weight": 2.9},
+            },
+            "pantry": {"flour": 1000000, "sugar": 1000000},
+        }))
+        .unwrap();
+
+        // Nothing beats 250000 of both a and b, which use up the whole pantry.
+        assert!(plan.value <= 1_500_000.0);
+        assert!(plan.value >= 0.99 * 1_500_000.0);
+    }
+
+    #[rstest]
+    #[case(
+        serde_json::json!({"cookie": {"recipe": {"flour": 1}, "weight": -1.0}}),
+        "Weight of cookie must not be negative, no cookies for Santa!",
+    )]
+    #[case(
+        serde_json::json!({"cookie": {"recipe": {"glitter": 1}, "units": {"glitter": "ml"}}}),
+        "No density for glitter, no cookies for Santa!",
+    )]
+    #[case(
+        serde_json::Value::Object(
+            (0..=MAX_PLAN_RECIPES)
+                .map(|i| (format!("cookie {i}"), serde_json::json!({"recipe": {"flour": 1}})))
+                .collect()
+        ),
+        "At most 16 recipes can be planned, no cookies for Santa!",
+    )]
+    fn test_plan_error(#[case] recipes: serde_json::Value, #[case] message: &str) {
+        let err = plan_json(serde_json::json!({
+            "recipes": recipes,
+            "pantry": {"flour": 10, "glitter": 10},
+            "units": {"flour": "g", "glitter": "g"},
+        }))
+        .unwrap_err();
+
+        assert_eq!(err, message);
+    }
+
+    #[test]
+    fn test_plan_too_many_ingredients() {
+        let pantry: serde_json::Map<_, _> = (0..=MAX_PLAN_INGREDIENTS)
+            .map(|i| (format!("spice {i}"), serde_json::json!(1)))
+            .collect();
+        let err = plan_json(serde_json::json!({
+            "recipes": {"cookie": {"recipe": {"spice 0": 1}}},
+            "pantry": pantry,
+        }))
+        .unwrap_err();
+
+        assert_eq!(
+            err,
+            "At most 32 pantry ingredients can be planned, no cookies for Santa!"
+        );
+    }
+
+    #[test]
+    fn test_plan_many_recipes() {
+        let pantry: serde_json::Map<_, _> = (0..MAX_PLAN_INGREDIENTS)
+            .map(|i| (format!("spice {i}"), serde_json::json!(1000)))
+            .collect();
+        // Every recipe has spices of its own, so each can bake as many cookies as fit.
+        let recipes: serde_json::Map<_, _> = (0..MAX_PLAN_RECIPES)
+            .map(|r| {
+                let recipe = serde_json::json!({
+                    format!("spice {r}"): 1 + r % 3,
+                    format!("spice {}", r + MAX_PLAN_RECIPES): 2,
+                });
+
+                (format!("cookie {r}"), serde_json::json!({"recipe": recipe}))
+            })
+            .collect();
+        let plan = plan_json(serde_json::json!({"recipes": recipes, "pantry": pantry})).unwrap();
+        let expected: Vec<IngredientUnit> = (0..MAX_PLAN_RECIPES as u64)
+            .map(|r| (1000 / (1 + r % 3)).min(500))
+            .collect();
+
+        assert!(plan.optimal);
+        assert_eq!(plan.plan.values().copied().collect::<Vec<_>>(), expected);
+    }
+
+    #[rstest]
+    #[case(
+        &[("flour", 95), ("sugar", 50), ("butter", 0)],
+        &[("flour", 385), ("sugar", 507), ("butter", 2)],
+        &[],
+        5,
+        &[("flour", 90), ("sugar", 0)],
+        &[],
+        &[("flour", 0), ("sugar", 257), ("butter", 2)],
+    )]
+    #[case(
+        &[("flour", 95), ("milk", 3)],
+        &[("flour", 385)],
+        &[("flour", 1000), ("milk", 4)],
+        5,
+        &[("flour", 90), ("milk", 15)],
+        &[("flour", 1), ("milk", 4)],
+        &[("flour", 910), ("milk", 1)],
+    )]
+    #[case(
+        &[("flour", 95)],
+        &[("flour", 385)],
+        &[("flour", 1000)],
+        0,
+        &[("flour", 0)],
+        &[("flour", 0)],
+        &[("flour", 385)],
+    )]
+    fn test_shopping(
+        #[case] recipe: &[(&str, IngredientUnit)],
+        #[case] pantry: &[(&str, IngredientUnit)],
+        #[case] packages: &[(&str, IngredientUnit)],
+        #[case] cookies: IngredientUnit,
+        #[case] shortfall: &[(&str, IngredientUnit)],
+        #[case] buy: &[(&str, IngredientUnit)],
+        #[case] left: &[(&str, IngredientUnit)],
+    ) {
+        let kitchen = Kitchen {
+            recipe: ingredients(recipe),
+            pantry: ingredients(pantry),
+            ..Default::default()
+        };
+        let list = kitchen.shopping(cookies, &ingredients(packages)).unwrap();
+
+        assert_eq!(list.shortfall, ingredients(shortfall));
+        assert_eq!(list.packages, ingredients(buy));
+        assert_eq!(list.baked.cookies, cookies);
+        assert_eq!(list.baked.pantry, ingredients(left));
+    }
+
+    #[test]
+    fn test_shopping_units() {
+        let request: ShoppingRequest = serde_json::from_value(serde_json::json!({
+            "recipe": {"flour": 250, "milk": 1},
+            "pantry": {"flour": 1, "milk": 100},
+            "units": {
+                "recipe": {"flour": "g", "milk": "cups"},
+                "pantry": {"flour": "kg", "milk": "ml"},
+            },
+            "cookies": 6,
+            "packages": {"milk": 500},
+        }))
+        .unwrap();
+        let list = request
+            .kitchen
+            .shopping(request.cookies, &request.packages)
+            .unwrap();
+
+        assert_eq!(list.shortfall, ingredients(&[("flour", 1), ("milk", 1320)]));
+        assert_eq!(list.packages, ingredients(&[("milk", 3)]));
+        assert_eq!(
+            list.baked.pantry,
+            ingredients(&[("flour", 0), ("milk", 180)])
+        );
+    }
+
+    #[test]
+    fn test_shopping_package_error() {
+        let kitchen = Kitchen {
+            recipe: ingredients(&[("flour", 1)]),
+            pantry: ingredients(&[("flour", 0)]),
+            ..Default::default()
+        };
+        let err = kitchen
+            .shopping(1, &ingredients(&[("flour", 0)]))
+            .unwrap_err();
+
+        assert_eq!(
+            err.error,
+            "Package size of flour must be positive, no cookies for Santa!"
+        );
+    }
+
+    #[rstest]
+    #[case(&[("flour", 2)], &[("flour", 0)], &[], u64::MAX)]
+    #[case(&[("flour", 1)], &[("flour", 0)], &[("flour", 2)], u64::MAX)]
+    #[case(&[("flour", 1)], &[("flour", 9)], &[("flour", 10)], u64::MAX)]
+    fn test_shopping_overflow(
+        #[case] recipe: &[(&str, IngredientUnit)],
+        #[case] pantry: &[(&str, IngredientUnit)],
+        #[case] packages: &[(&str, IngredientUnit)],
+        #[case] cookies: IngredientUnit,
+    ) {
+        let kitchen = Kitchen {
+            recipe: ingredients(recipe),
+            pantry: ingredients(pantry),
+            ..Default::default()
+        };
+        let err = kitchen
+            .shopping(cookies, &ingredients(packages))
+            .unwrap_err();
+
+        assert_eq!(
+            err.error,
+            "Too much flour to shop for, no cookies for Santa!"
+        );
+    }
+
+    #[rstest]
+    #[case(Conversion::Exact { recipe_base: 1, pantry_base: 1 }, 1, Some(u64::MAX))]
+    #[case(Conversion::Exact { recipe_base: 2, pantry_base: 1 }, 1, None)]
+    #[case(Conversion::Exact { recipe_base: 1, pantry_base: 2 }, 2, Some(u64::MAX))]
+    #[case(Conversion::Density { factor: 0.5 }, 1, Some(1 << 63))]
+    #[case(Conversion::Density { factor: 1.0 }, 1, None)]
+    fn test_conversion_needed_overflow(
+        #[case] conversion: Conversion,
+        #[case] quantity: IngredientUnit,
+        #[case] expected: Option<IngredientUnit>,
+    ) {
+        assert_eq!(conversion.needed(u64::MAX, quantity), expected);
+    }
 }
@@ -1,31 +1,289 @@
-use std::{env, fs};
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::{env, fmt, fs, thread};
 
+use rocket::fs::relative;
+use rocket::futures::stream::{self, StreamExt};
 use rocket::http::Status;
-use rocket::{get, State};
-use rustemon::client::{CACacheManager, CacheMode, RustemonClient, RustemonClientBuilder};
+use rocket::request::{self, FromParam, FromRequest};
+use rocket::serde::{json::Json, Deserialize, Serialize};
+use rocket::{delete, get, post, FromForm, FromFormField, Request, State};
+use rustemon::client::{
+    CACacheManager, CacheMode, Environment, RustemonClient, RustemonClientBuilder,
+};
 use rustemon::error::Error;
 use rustemon::model::pokemon::Pokemon;
 use rustemon::pokemon::pokemon;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
 
-fn rustemon_server_error(error: Error) -> (Status, String) {
+fn rustemon_server_error(error: Error, id: &PokemonId) -> (Status, String) {
     match error {
         Error::FollowEmptyURL => (Status::InternalServerError, "Empty URL".to_owned()),
         Error::NoTrailingSlash(message) => (Status::InternalServerError, message),
         Error::UrlParse(message) => (Status::InternalServerError, message),
+        Error::Reqwest(reqwest_error)
+            if reqwest_error.status() == Some(reqwest::StatusCode::NOT_FOUND) =>
+        {
+            (Status::NotFound, format!("Pokémon {id} was not found"))
+        }
         Error::Reqwest(reqwest_error) => (Status::BadGateway, reqwest_error.to_string()),
         Error::ReqwestMiddleware(reqwest_error) => (Status::BadGateway, reqwest_error.to_string()),
     }
 }
 
-#[get("/weight/<pokedex_number>")]
+type PokemonResult = Result<Pokemon, (Status, String)>;
+
+const POKEAPI: &str = "https://pokeapi.co/api/v2/";
+
+/// Default forms of Pokémon that PokeAPI only knows by form, and other spellings.
+const POKEMON_ALIASES: &[(&str, &str)] = &[
+    ("nidoran-female", "nidoran-f"),
+    ("nidoran-male", "nidoran-m"),
+    ("deoxys", "deoxys-normal"),
+    ("wormadam", "wormadam-plant"),
+    ("giratina", "giratina-altered"),
+    ("shaymin", "shaymin-land"),
+    ("basculin", "basculin-red-striped"),
+    ("darmanitan", "darmanitan-standard"),
+    ("tornadus", "tornadus-incarnate"),
+    ("thundurus", "thundurus-incarnate"),
+    ("landorus", "landorus-incarnate"),
+    ("keldeo", "keldeo-ordinary"),
+    ("meloetta", "meloetta-aria"),
+    ("aegislash", "aegislash-shield"),
+    ("pumpkaboo", "pumpkaboo-average"),
+    ("gourgeist", "gourgeist-average"),
+    ("lycanroc", "lycanroc-midday"),
+    ("mimikyu", "mimikyu-disguised"),
+    ("toxtricity", "toxtricity-amped"),
+    ("urshifu", "urshifu-single-strike"),
+];
+
+/// A Pokémon by Pokédex number, or by name or alias in any case.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PokemonId {
+    Number(i64),
+    /// A PokeAPI name, like `mr-mime` for `Mr. Mime`.
+    Name(String),
+}
+
+impl PokemonId {
+    fn parse(query: &str) -> Option<PokemonId> {
+        let query = query.trim();
+
+        if let Ok(number) = query.strip_prefix('#').unwrap_or(query).parse() {
+            return Some(PokemonId::Number(number));
+        }
+
+        let mut name = String::new();
+
+        for c in query.nfkd().filter(|&c| !is_combining_mark(c)) {
+            match c {
+                '♀' => name.push_str("-f"),
+                '♂' => name.push_str("-m"),
+                '.' | '\'' | '’' | ':' => {}
+                c if c.is_whitespace() || c == '_' || c == '-' => {
+                    if !name.is_empty() && !name.ends_with('-') {
+                        name.push('-');
+                    }
+                }
+                c => name.extend(c.to_lowercase()),
+            }
+        }
+
+        let name = name.trim_end_matches('-');
+        let name = (POKEMON_ALIASES.iter())
+            .find(|(alias, _)| *alias == name)
+            .map_or(name, |(_, name)| name);
+
+        (!name.is_empty()).then(|| PokemonId::Name(name.to_owned()))
+    }
+}
+
+impl<'a> FromParam<'a> for PokemonId {
+    type Error = &'a str;
+
+    fn from_param(param: &'a str) -> Result<Self, Self::Error> {
+        PokemonId::parse(param).ok_or(param)
+    }
+}
+
+impl fmt::Display for PokemonId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PokemonId::Number(number) => write!(f, "{number}"),
+            PokemonId::Name(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+/// Looks up Pokémon by their Pokédex number or name.
+#[rocket::async_trait]
+pub trait PokemonSource: Send + Sync {
+    async fn pokemon(&self, pokedex_number: i64) -> PokemonResult;
+
+    async fn pokemon_by_name(&self, name: &str) -> PokemonResult;
+
+    async fn lookup(&self, id: &PokemonId) -> PokemonResult {
+        match id {
+            PokemonId::Number(number) => self.pokemon(*number).await,
+            PokemonId::Name(name) => self.pokemon_by_name(name).await,
+        }
+    }
+
+    /// The cache behind this source, for the cache admin routes.
+    fn cache(&self) -> Option<&CachedRustemon> {
+        None
+    }
+}
+
+#[rocket::async_trait]
+impl PokemonSource for RustemonClient {
+    async fn pokemon(&self, pokedex_number: i64) -> PokemonResult {
+        pokemon::get_by_id(pokedex_number, self)
+            .await
+            .map_err(|err| rustemon_server_error(err, &PokemonId::Number(pokedex_number)))
+    }
+
+    async fn pokemon_by_name(&self, name: &str) -> PokemonResult {
+        pokemon::get_by_name(name, self)
+            .await
+            .map_err(|err| rustemon_server_error(err, &PokemonId::Name(name.to_owned())))
+    }
+}
+
+/// PokeAPI `pokemon` documents, keyed by Pokédex number.
+#[derive(Debug, Default)]
+pub struct PokemonFixtures {
+    documents: HashMap<i64, String>,
+    numbers: HashMap<String, i64>,
+}
+
+impl PokemonFixtures {
+    /// Loads every `.json` document in `dir`, failing on any that isn't a Pokémon.
+    pub fn load(dir: &Path) -> io::Result<PokemonFixtures> {
+        let mut documents = HashMap::new();
+        let mut numbers = HashMap::new();
+
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+
+            if path
+                .extension()
+                .map_or(true, |extension| extension != "json")
+            {
+                continue;
+            }
+
+            let document = fs::read_to_string(&path)?;
+            let pokemon: Pokemon = serde_json::from_str(&document).map_err(|err| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}: {err}", path.display()),
+                )
+            })?;
+
+            numbers.insert(pokemon.name, pokemon.id);
+            documents.insert(pokemon.id, document);
+        }
+
+        Ok(PokemonFixtures { documents, numbers })
+    }
+
+    fn document(&self, id: &PokemonId) -> Option<&str> {
+        let number = match id {
+            PokemonId::Number(number) => number,
+            PokemonId::Name(name) => self.numbers.get(name)?,
+        };
+
+        self.documents.get(number).map(String::as_str)
+    }
+}
+
+#[rocket::async_trait]
+impl PokemonSource for PokemonFixtures {
+    async fn pokemon(&self, pokedex_number: i64) -> PokemonResult {
+        self.lookup(&PokemonId::Number(pokedex_number)).await
+    }
+
+    async fn pokemon_by_name(&self, name: &str) -> PokemonResult {
+        self.lookup(&PokemonId::Name(name.to_owned())).await
+    }
+
+    async fn lookup(&self, id: &PokemonId) -> PokemonResult {
+        let document = (self.document(id))
+            .ok_or_else(|| (Status::NotFound, format!("Pokémon {id} was not found")))?;
+
+        serde_json::from_str(document).map_err(|err| (Status::InternalServerError, err.to_string()))
+    }
+}
+
+/// A stand-in for PokeAPI, serving [`PokemonFixtures`] over HTTP on a local port.
+///
+/// Unknown Pokémon get a plain text 404, like PokeAPI does.
+pub struct MockPokeApi {
+    base: String,
+}
+
+impl MockPokeApi {
+    /// Serves `fixtures` from a background thread for the rest of the process.
+    pub fn spawn(fixtures: PokemonFixtures) -> io::Result<MockPokeApi> {
+        let listener = TcpListener::bind(("127.0.0.1", 0))?;
+        let base = format!("http://{}/api/v2/", listener.local_addr()?);
+        let fixtures = Arc::new(fixtures);
+
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let fixtures = fixtures.clone();
+
+                thread::spawn(move || MockPokeApi::respond(&fixtures, stream));
+            }
+        });
+
+        Ok(MockPokeApi { base })
+    }
+
+    fn respond(fixtures: &PokemonFixtures, mut stream: TcpStream) -> io::Result<()> {
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut request_line = String::new();
+
+        reader.read_line(&mut request_line)?;
+
+        // Skip the headers, lookups have no body.
+        let mut header = String::new();
+
+        while reader.read_line(&mut header)? > 2 {
+            header.clear();
+        }
+
+        let document = (request_line.split_whitespace().nth(1))
+            .and_then(|path| path.strip_prefix("/api/v2/pokemon/"))
+            .and_then(|id| PokemonId::parse(id.trim_end_matches('/')))
+            .and_then(|id| fixtures.document(&id));
+        let (status, content_type, body) = match document {
+            Some(document) => ("200 OK", "application/json", document),
+            None => ("404 Not Found", "text/plain", "Not Found"),
+        };
+
+        write!(
+            stream,
+            "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        )
+    }
+}
+
+#[get("/weight/<pokemon>")]
 async fn pokemon_weight(
-    pokedex_number: i64,
-    rustemon_client: &State<RustemonClient>,
+    pokemon: PokemonId,
+    pokemon_source: &State<Box<dyn PokemonSource>>,
 ) -> (Status, String) {
-    let pokemon_result: Result<Pokemon, Error> =
-        pokemon::get_by_id(pokedex_number, rustemon_client).await;
-
-    match pokemon_result {
+    match pokemon_source.lookup(&pokemon).await {
         Ok(pokemon) => {
             // The weight of this Pokémon in hectograms.
             let weight = pokemon.weight as f64;
@@ -33,61 +291,758 @@ async fn pokemon_weight(
 
             (Status::Ok, format!("{}", kilograms))
         }
-        Err(error) => rustemon_server_error(error),
+        Err(error) => error,
+    }
+}
+
+/// Surface gravity presets, in m/s².
+#[derive(Debug, Clone, Copy, PartialEq, FromFormField)]
+enum Planet {
+    Mercury,
+    Venus,
+    Earth,
+    Moon,
+    Mars,
+    Jupiter,
+    Saturn,
+    Uranus,
+    Neptune,
+    Pluto,
+}
+
+impl Planet {
+    fn gravity(self) -> f64 {
+        match self {
+            Planet::Mercury => 3.7,
+            Planet::Venus => 8.87,
+            Planet::Earth => EARTH_GRAVITY,
+            Planet::Moon => 1.62,
+            Planet::Mars => 3.721,
+            Planet::Jupiter => 24.79,
+            Planet::Saturn => 10.44,
+            Planet::Uranus => 8.69,
+            Planet::Neptune => 11.15,
+            Planet::Pluto => 0.62,
+        }
+    }
+}
+
+/// g = 9.825 m/s²
+const EARTH_GRAVITY: f64 = 9.825;
+/// Drop from a 10-meter high chimney.
+const CHIMNEY_HEIGHT: f64 = 10.0;
+/// The drag coefficient of a sphere.
+const SPHERE_DRAG_COEFFICIENT: f64 = 0.47;
+/// Air density at sea level and 15 °C, in kg/m³.
+const AIR_DENSITY: f64 = 1.225;
+
+/// How a Pokémon is dropped, by default down a chimney on Earth, in a vacuum.
+///
+/// With `drag`, air resists the fall in proportion to the squared velocity, across a sphere
+/// as tall as the Pokémon.
+#[derive(Debug, Default, FromForm)]
+struct DropPhysics {
+    height: Option<f64>,
+    gravity: Option<f64>,
+    planet: Option<Planet>,
+    drag: Option<bool>,
+    drag_coefficient: Option<f64>,
+    air_density: Option<f64>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+struct Drag {
+    coefficient: f64,
+    air_density: f64,
+    /// Cross-section in m².
+    cross_section: f64,
+    terminal_velocity: f64,
+}
+
+/// The fall of a Pokémon, in SI units.
+#[derive(Debug, Serialize, PartialEq)]
+struct DropBreakdown {
+    pokemon: String,
+    mass: f64,
+    height: f64,
+    gravity: f64,
+    drag: Option<Drag>,
+    velocity: f64,
+    time: f64,
+    momentum: f64,
+    energy: f64,
+}
+
+fn positive(name: &str, value: f64) -> Result<f64, (Status, String)> {
+    if value.is_finite() && value > 0.0 {
+        Ok(value)
+    } else {
+        Err((Status::BadRequest, format!("{name} must be positive")))
+    }
+}
+
+impl DropPhysics {
+    fn drop(&self, pokemon: &Pokemon) -> Result<DropBreakdown, (Status, String)> {
+        let height = positive("height", self.height.unwrap_or(CHIMNEY_HEIGHT))?;
+        let gravity = match (self.gravity, self.planet) {
+            (Some(_), Some(_)) => {
+                return Err((
+                    Status::BadRequest,
+                    "Use either gravity or planet".to_owned(),
+                ))
+            }
+            (Some(gravity), None) => positive("gravity", gravity)?,
+            (None, planet) => planet.unwrap_or(Planet::Earth).gravity(),
+        };
+        // The weight of this Pokémon in hectograms.
+        let mass = pokemon.weight as f64 / 10.0;
+        // The height of this Pokémon in decimetres.
+        let diameter = pokemon.height as f64 / 10.0;
+        let drag = if self.drag.unwrap_or_default() {
+            let coefficient = positive(
+                "drag_coefficient",
+                self.drag_coefficient.unwrap_or(SPHERE_DRAG_COEFFICIENT),
+            )?;
+            let air_density = positive("air_density", self.air_density.unwrap_or(AIR_DENSITY))?;
+            let cross_section = std::f64::consts::PI * (diameter / 2.0).powi(2);
+            // Where drag balances gravity, √(2mg / ρCA).
+            let terminal_velocity =
+                f64::sqrt(2.0 * mass * gravity / (air_density * coefficient * cross_section));
+
+            Some(Drag {
+                coefficient,
+                air_density,
+                cross_section,
+                terminal_velocity,
+            })
+        } else {
+            None
+        };
+        let (velocity, time) = match &drag {
+            Some(drag) if drag.terminal_velocity > 0.0 && drag.terminal_velocity.is_finite() => {
+                let terminal = drag.terminal_velocity;
+                // h = vₜ²/g · ln cosh(gt / vₜ), so cosh(gt / vₜ) = eˣ.
+                let x = gravity * height / (terminal * terminal);
+                let decay = f64::exp(-2.0 * x);
+                // acosh(eˣ) without overflowing eˣ.
+                let time = terminal / gravity * (x + f64::ln(1.0 + f64::sqrt(1.0 - decay)));
+
+                (terminal * f64::sqrt(1.0 - decay), time)
+            }
+            // In a vacuum, and for massless or pointlike Pokémon, v = √(2h * g).
+            _ => (
+                f64::sqrt(2.0 * height * gravity),
+                f64::sqrt(2.0 * height / gravity),
+            ),
+        };
+
+        Ok(DropBreakdown {
+            pokemon: pokemon.name.clone(),
+            mass,
+            height,
+            gravity,
+            drag,
+            velocity,
+            time,
+            // momentum, measured in Newton-seconds = kg * m/s
+            momentum: mass * velocity,
+            // kinetic energy, measured in joules = ½ kg * (m/s)²
+            energy: 0.5 * mass * velocity * velocity,
+        })
     }
 }
 
-#[get("/drop/<pokedex_number>")]
+#[get("/drop/<pokemon>?<physics..>")]
 async fn drop_pokemon(
-    pokedex_number: i64,
-    rustemon_client: &State<RustemonClient>,
+    pokemon: PokemonId,
+    physics: DropPhysics,
+    pokemon_source: &State<Box<dyn PokemonSource>>,
 ) -> (Status, String) {
-    let pokemon_result: Result<Pokemon, Error> =
-        pokemon::get_by_id(pokedex_number, rustemon_client).await;
+    let breakdown =
+        (pokemon_source.lookup(&pokemon).await).and_then(|pokemon| physics.drop(&pokemon));
 
-    match pokemon_result {
-        Ok(pokemon) => {
-            // The weight of this Pokémon in hectograms.
-            let weight = pokemon.weight as f64;
-            let kilograms = weight / 10.0f64;
-            // g = 9.825 m/s²
-            const GRAVITY: f64 = 9.825;
-            // drop from 10-meter high chimney
-            const HEIGHT: f64 = 10.0;
-            // v = √(2h * g)
-            let velocity = f64::sqrt(2.0f64 * HEIGHT * GRAVITY);
-            // momentum, measured in Newton-seconds = kg * m/s
-            let momentum = kilograms * velocity;
+    match breakdown {
+        Ok(breakdown) => (Status::Ok, format!("{}", breakdown.momentum)),
+        Err(error) => error,
+    }
+}
+
+#[get("/drop/<pokemon>/breakdown?<physics..>")]
+async fn drop_breakdown(
+    pokemon: PokemonId,
+    physics: DropPhysics,
+    pokemon_source: &State<Box<dyn PokemonSource>>,
+) -> Result<Json<DropBreakdown>, (Status, String)> {
+    let pokemon = pokemon_source.lookup(&pokemon).await?;
+
+    physics.drop(&pokemon).map(Json)
+}
+
+/// Lookups in flight at once, unless a batch asks for another limit.
+const DEFAULT_BATCH_CONCURRENCY: usize = 8;
+const MAX_BATCH_CONCURRENCY: usize = 32;
+const MAX_BATCH_SIZE: usize = 100;
+
+/// A Pokédex number or name, as given in a batch.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+enum PokemonQuery {
+    Number(i64),
+    Name(String),
+}
+
+#[derive(Debug, Deserialize)]
+struct PokemonBatch {
+    pokemon: Vec<PokemonQuery>,
+    concurrency: Option<usize>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(untagged)]
+enum BatchItem {
+    Found {
+        query: PokemonQuery,
+        id: i64,
+        name: String,
+        weight: f64,
+        momentum: f64,
+    },
+    Failed {
+        query: PokemonQuery,
+        status: u16,
+        error: String,
+    },
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+struct BatchReport {
+    results: Vec<BatchItem>,
+    failed: usize,
+}
+
+/// Weighs and drops many Pokémon, looking them up concurrently.
+///
+/// Results keep the order of the batch, and a failed lookup only fails its own item.
+#[post("/batch?<physics..>", data = "<batch>")]
+async fn batch_pokemon(
+    batch: Json<PokemonBatch>,
+    physics: DropPhysics,
+    pokemon_source: &State<Box<dyn PokemonSource>>,
+) -> Result<Json<BatchReport>, (Status, String)> {
+    let PokemonBatch {
+        pokemon,
+        concurrency,
+    } = batch.into_inner();
+
+    if pokemon.len() > MAX_BATCH_SIZE {
+        return Err((
+            Status::BadRequest,
+            format!("At most {MAX_BATCH_SIZE} Pokémon per batch"),
+        ));
+    }
+
+    // Reject invalid physics once, rather than for every Pokémon.
+    physics.drop(&Pokemon::default())?;
+
+    let concurrency =
+        (concurrency.unwrap_or(DEFAULT_BATCH_CONCURRENCY)).clamp(1, MAX_BATCH_CONCURRENCY);
+    let physics = &physics;
+    let results: Vec<BatchItem> = stream::iter(pokemon)
+        .map(|query| async move {
+            let id = match &query {
+                PokemonQuery::Number(number) => Some(PokemonId::Number(*number)),
+                PokemonQuery::Name(name) => PokemonId::parse(name),
+            };
+            let breakdown = match id {
+                Some(id) => (pokemon_source.lookup(&id).await)
+                    .and_then(|pokemon| Ok((physics.drop(&pokemon)?, pokemon.id))),
+                None => Err((Status::BadRequest, "Invalid Pokémon".to_owned())),
+            };
+
+            match breakdown {
+                Ok((breakdown, id)) => BatchItem::Found {
+                    query,
+                    id,
+                    name: breakdown.pokemon,
+                    weight: breakdown.mass,
+                    momentum: breakdown.momentum,
+                },
+                Err((status, error)) => BatchItem::Failed {
+                    query,
+                    status: status.code,
+                    error,
+                },
+            }
+        })
+        .buffered(concurrency)
+        .collect()
+        .await;
+    let failed = (results.iter())
+        .filter(|item| matches!(item, BatchItem::Failed { .. }))
+        .count();
+
+    Ok(Json(BatchReport { results, failed }))
+}
+
+/// How rustemon caches PokeAPI responses, mirroring [`CacheMode`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RustemonCacheMode {
+    Default,
+    NoStore,
+    Reload,
+    NoCache,
+    ForceCache,
+    OnlyIfCached,
+}
+
+impl RustemonCacheMode {
+    fn cache_mode(self) -> CacheMode {
+        match self {
+            RustemonCacheMode::Default => CacheMode::Default,
+            RustemonCacheMode::NoStore => CacheMode::NoStore,
+            RustemonCacheMode::Reload => CacheMode::Reload,
+            RustemonCacheMode::NoCache => CacheMode::NoCache,
+            RustemonCacheMode::ForceCache => CacheMode::ForceCache,
+            RustemonCacheMode::OnlyIfCached => CacheMode::OnlyIfCached,
+        }
+    }
+
+    /// Whether a cached response answers a lookup without asking PokeAPI.
+    fn reads_cache(self) -> bool {
+        matches!(
+            self,
+            RustemonCacheMode::Default
+                | RustemonCacheMode::ForceCache
+                | RustemonCacheMode::OnlyIfCached
+        )
+    }
+}
+
+/// The `pokemon.cache` config table.
+#[derive(Debug, Default, Deserialize)]
+pub struct CacheConfig {
+    /// `force-cache` by default for PokeAPI, and `no-store` for the mock PokeAPI.
+    mode: Option<RustemonCacheMode>,
+    /// `cch23/rustemon-cache` in the temp dir by default.
+    path: Option<PathBuf>,
+}
 
-            (Status::Ok, format!("{}", momentum))
+/// A [`RustemonClient`] that counts its cache hits and misses.
+///
+/// A lookup is a hit when its response was cached beforehand, even if PokeAPI then
+/// revalidates it. Lookups by number and by name are cached separately.
+pub struct CachedRustemon {
+    client: RustemonClient,
+    base: String,
+    mode: RustemonCacheMode,
+    path: PathBuf,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+fn cache_error(err: impl ToString) -> (Status, String) {
+    (Status::InternalServerError, err.to_string())
+}
+
+/// The size of the files under `path`, or 0 if there is nothing there.
+fn disk_size(path: &Path) -> io::Result<u64> {
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(err) => return Err(err),
+    };
+    let mut size = 0;
+
+    for entry in entries {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+
+        size += if metadata.is_dir() {
+            disk_size(&entry.path())?
+        } else {
+            metadata.len()
+        };
+    }
+
+    Ok(size)
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+struct CacheStats {
+    mode: RustemonCacheMode,
+    path: PathBuf,
+    hits: u64,
+    misses: u64,
+    entries: usize,
+    /// Bytes on disk, including content no entry points to anymore.
+    size: u64,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+struct CachePurge {
+    purged: usize,
+}
+
+impl CachedRustemon {
+    /// The key http-cache stores the response to a lookup under.
+    fn key(&self, id: &PokemonId) -> String {
+        format!("GET:{}pokemon/{id}", self.base)
+    }
+
+    async fn count(&self, id: &PokemonId) {
+        let cached = self.mode.reads_cache()
+            && matches!(
+                cacache::metadata(&self.path, self.key(id)).await,
+                Ok(Some(_))
+            );
+        let counter = if cached { &self.hits } else { &self.misses };
+
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    async fn stats(&self) -> Result<CacheStats, (Status, String)> {
+        let path = self.path.clone();
+        let (entries, size) = rocket::tokio::task::spawn_blocking(move || {
+            let entries = cacache::list_sync(&path).filter(Result::is_ok).count();
+
+            disk_size(&path).map(|size| (entries, size))
+        })
+        .await
+        .map_err(cache_error)?
+        .map_err(cache_error)?;
+
+        Ok(CacheStats {
+            mode: self.mode,
+            path: self.path.clone(),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries,
+            size,
+        })
+    }
+
+    /// Removes the cached response to one lookup, with its content.
+    async fn purge(&self, id: &PokemonId) -> Result<CachePurge, (Status, String)> {
+        let key = self.key(id);
+        let Some(metadata) = (cacache::metadata(&self.path, &key).await).map_err(cache_error)?
+        else {
+            return Ok(CachePurge { purged: 0 });
+        };
+
+        cacache::remove(&self.path, &key)
+            .await
+            .map_err(cache_error)?;
+        cacache::remove_hash(&self.path, &metadata.integrity)
+            .await
+            .map_err(cache_error)?;
+
+        Ok(CachePurge { purged: 1 })
+    }
+
+    /// Maps a failed lookup, asking PokeAPI for the status when the response didn't decode.
+    ///
+    /// rustemon decodes every response without checking its status, so the plain text 404
+    /// PokeAPI sends for an unknown Pokémon fails to decode just like an error page would.
+    async fn lookup_error(&self, error: Error, id: &PokemonId) -> (Status, String) {
+        match error {
+            Error::Reqwest(reqwest_error) if reqwest_error.is_decode() => {
+                let status = reqwest::get(format!("{}pokemon/{id}", self.base))
+                    .await
+                    .map(|response| response.status());
+
+                match status {
+                    Ok(reqwest::StatusCode::NOT_FOUND) => {
+                        (Status::NotFound, format!("Pokémon {id} was not found"))
+                    }
+                    _ => (Status::BadGateway, reqwest_error.to_string()),
+                }
+            }
+            error => rustemon_server_error(error, id),
+        }
+    }
+
+    async fn purge_all(&self) -> Result<CachePurge, (Status, String)> {
+        let path = self.path.clone();
+        let purged = rocket::tokio::task::spawn_blocking(move || {
+            let purged = cacache::list_sync(&path).filter(Result::is_ok).count();
+
+            cacache::clear_sync(&path).map(|_| purged)
+        })
+        .await
+        .map_err(cache_error)?
+        .map_err(cache_error)?;
+
+        Ok(CachePurge { purged })
+    }
+}
+
+#[rocket::async_trait]
+impl PokemonSource for CachedRustemon {
+    async fn pokemon(&self, pokedex_number: i64) -> PokemonResult {
+        let id = PokemonId::Number(pokedex_number);
+
+        self.count(&id).await;
+
+        match pokemon::get_by_id(pokedex_number, &self.client).await {
+            Ok(pokemon) => Ok(pokemon),
+            Err(err) => Err(self.lookup_error(err, &id).await),
         }
-        Err(error) => rustemon_server_error(error),
+    }
+
+    async fn pokemon_by_name(&self, name: &str) -> PokemonResult {
+        let id = PokemonId::Name(name.to_owned());
+
+        self.count(&id).await;
+
+        match pokemon::get_by_name(name, &self.client).await {
+            Ok(pokemon) => Ok(pokemon),
+            Err(err) => Err(self.lookup_error(err, &id).await),
+        }
+    }
+
+    fn cache(&self) -> Option<&CachedRustemon> {
+        Some(self)
     }
 }
 
-pub fn init_rustemon_client() -> RustemonClient {
-    let cache = env::temp_dir().join("cch23/rustemon-cache");
+pub fn init_rustemon_client(
+    config: &CacheConfig,
+    base: &str,
+    mode: RustemonCacheMode,
+) -> CachedRustemon {
+    let mode = config.mode.unwrap_or(mode);
+    let cache =
+        (config.path.clone()).unwrap_or_else(|| env::temp_dir().join("cch23/rustemon-cache"));
 
     if !cache.exists() {
         fs::create_dir_all(&cache).expect("Unable to create RustemonClinet cache directory");
     }
 
     let client = RustemonClientBuilder::default()
-        .with_mode(CacheMode::ForceCache)
-        .with_manager(CACacheManager { path: cache })
+        .with_mode(mode.cache_mode())
+        .with_manager(CACacheManager {
+            path: cache.clone(),
+        })
+        .with_environment(Environment::Custom(base.to_owned()))
         .try_build()
         .expect("Unable to build RustemonClinet");
 
-    client
+    CachedRustemon {
+        client,
+        base: base.to_owned(),
+        mode,
+        path: cache,
+        hits: AtomicU64::new(0),
+        misses: AtomicU64::new(0),
+    }
+}
+
+fn rustemon_cache(
+    pokemon_source: &State<Box<dyn PokemonSource>>,
+) -> Result<&CachedRustemon, (Status, String)> {
+    (pokemon_source.cache()).ok_or_else(|| {
+        (
+            Status::NotFound,
+            "This Pokémon source has no cache".to_owned(),
+        )
+    })
+}
+
+/// The secret that cache admin requests must present, or `None` to refuse all of them.
+pub struct CacheAdminToken(pub Option<String>);
+
+/// Proof that a request carries `Authorization: Bearer <token>` with the [`CacheAdminToken`].
+struct CacheAdmin;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for CacheAdmin {
+    type Error = (Status, String);
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        let expected = (request.rocket().state::<CacheAdminToken>())
+            .and_then(|CacheAdminToken(token)| token.as_deref());
+        let presented = (request.headers().get_one("Authorization"))
+            .and_then(|value| value.strip_prefix("Bearer "));
+        let refuse = |status: Status, message: &str| {
+            request::Outcome::Error((status, (status, message.to_owned())))
+        };
+
+        match (expected, presented) {
+            (None, _) => refuse(Status::Forbidden, "Cache admin is disabled"),
+            (_, None) => refuse(Status::Unauthorized, "A cache admin token is required"),
+            // Compare every byte, so the time taken doesn't tell how much of the token matched.
+            (Some(expected), Some(presented))
+                if expected.len() == presented.len()
+                    && (expected.bytes().zip(presented.bytes()))
+                        .fold(0, |diff, (a, b)| diff | (a ^ b))
+                        == 0 =>
+            {
+                request::Outcome::Success(CacheAdmin)
+            }
+            (Some(_), Some(_)) => refuse(Status::Forbidden, "Invalid cache admin token"),
+        }
+    }
+}
+
+/// The cache stats, which give away where the cache is on disk, so they're for admins only.
+#[get("/cache")]
+async fn cache_stats(
+    admin: Result<CacheAdmin, (Status, String)>,
+    pokemon_source: &State<Box<dyn PokemonSource>>,
+) -> Result<Json<CacheStats>, (Status, String)> {
+    let CacheAdmin = admin?;
+
+    rustemon_cache(pokemon_source)?.stats().await.map(Json)
+}
+
+const MAX_WARM_SIZE: i64 = 2000;
+
+#[derive(Debug, Serialize, PartialEq)]
+struct CacheWarm {
+    warmed: usize,
+    failed: Vec<BatchItem>,
+}
+
+/// Looks up the Pokémon from `from` to `to` inclusive, so later lookups hit the cache.
+#[post("/cache/warm?<from>&<to>")]
+async fn warm_cache(
+    admin: Result<CacheAdmin, (Status, String)>,
+    from: i64,
+    to: i64,
+    pokemon_source: &State<Box<dyn PokemonSource>>,
+) -> Result<Json<CacheWarm>, (Status, String)> {
+    let CacheAdmin = admin?;
+    let cache = rustemon_cache(pokemon_source)?;
+
+    if from < 1 || to < from {
+        return Err((Status::BadRequest, format!("Invalid range {from} to {to}")));
+    }
+
+    if to - from >= MAX_WARM_SIZE {
+        return Err((
+            Status::BadRequest,
+            format!("At most {MAX_WARM_SIZE} Pokémon per warm-up"),
+        ));
+    }
+
+    let lookups: Vec<_> = stream::iter(from..=to)
+        .map(|pokedex_number| async move { (pokedex_number, cache.pokemon(pokedex_number).await) })
+        .buffered(DEFAULT_BATCH_CONCURRENCY)
+        .collect()
+        .await;
+    let failed: Vec<BatchItem> = (lookups.into_iter())
+        .filter_map(|(pokedex_number, lookup)| {
+            let (status, error) = lookup.err()?;
+
+            Some(BatchItem::Failed {
+                query: PokemonQuery::Number(pokedex_number),
+                status: status.code,
+                error,
+            })
+        })
+        .collect();
+
+    Ok(Json(CacheWarm {
+        warmed: (to - from + 1) as usize - failed.len(),
+        failed,
+    }))
+}
+
+#[delete("/cache")]
+async fn purge_cache(
+    admin: Result<CacheAdmin, (Status, String)>,
+    pokemon_source: &State<Box<dyn PokemonSource>>,
+) -> Result<Json<CachePurge>, (Status, String)> {
+    let CacheAdmin = admin?;
+
+    rustemon_cache(pokemon_source)?.purge_all().await.map(Json)
+}
+
+#[delete("/cache/<pokemon>")]
+async fn purge_cached_pokemon(
+    admin: Result<CacheAdmin, (Status, String)>,
+    pokemon: PokemonId,
+    pokemon_source: &State<Box<dyn PokemonSource>>,
+) -> Result<Json<CachePurge>, (Status, String)> {
+    let CacheAdmin = admin?;
+
+    rustemon_cache(pokemon_source)?
+        .purge(&pokemon)
+        .await
+        .map(Json)
+}
+
+/// Which [`PokemonSource`] to use, read from the `pokemon` config table.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PokemonBackend {
+    /// PokeAPI itself, through a [`CachedRustemon`].
+    #[default]
+    Live,
+    /// The fixture documents, read directly.
+    Fixture,
+    /// A [`MockPokeApi`] serving the fixture documents, through a [`CachedRustemon`].
+    Mock,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct PokemonConfig {
+    #[serde(default)]
+    source: PokemonBackend,
+    /// Directory of PokeAPI `pokemon` documents, `fixtures/pokemon` by default.
+    fixtures: Option<PathBuf>,
+    #[serde(default)]
+    cache: CacheConfig,
+}
+
+pub fn create_pokemon_source(config: PokemonConfig) -> Box<dyn PokemonSource> {
+    let fixtures = || {
+        let dir = (config.fixtures.clone())
+            .unwrap_or_else(|| PathBuf::from(relative!("fixtures/pokemon")));
+
+        PokemonFixtures::load(&dir).expect("Unable to load Pokémon fixtures")
+    };
+
+    match config.source {
+        PokemonBackend::Live => Box::new(init_rustemon_client(
+            &config.cache,
+            POKEAPI,
+            RustemonCacheMode::ForceCache,
+        )),
+        PokemonBackend::Fixture => Box::new(fixtures()),
+        PokemonBackend::Mock => {
+            let mock = MockPokeApi::spawn(fixtures()).expect("Unable to start the mock PokeAPI");
+
+            Box::new(init_rustemon_client(
+                &config.cache,
+                &mock.base,
+                RustemonCacheMode::NoStore,
+            ))
+        }
+    }
 }
 
 pub fn routes() -> Vec<rocket::Route> {
-    rocket::routes![pokemon_weight, drop_pokemon]
+    rocket::routes![
+        pokemon_weight,
+        drop_pokemon,
+        drop_breakdown,
+        batch_pokemon,
+        cache_stats,
+        warm_cache,
+        purge_cache,
+        purge_cached_pokemon
+    ]
 }
 
 #[cfg(test)]
 mod tests_day_08 {
     use super::*;
+    use rocket::http::Header;
+    use rocket::local::blocking::Client;
+    use rstest::*;
+    use std::sync::atomic::AtomicUsize;
 
     /// Enforce traits needed for rocket to manage state of <T>
     fn is_manage_safe<T: Send + Sync + 'static>() {}
@@ -95,5 +1050,600 @@ mod tests_day_08 {
     #[test]
     fn test_rustemon_client_is_manage_safe() {
         is_manage_safe::<RustemonClient>();
+        is_manage_safe::<Box<dyn PokemonSource>>();
+    }
+
+    const ADMIN_TOKEN: &str = "let-me-in";
+
+    fn admin() -> Header<'static> {
+        Header::new("Authorization", format!("Bearer {ADMIN_TOKEN}"))
+    }
+
+    fn client(source: PokemonBackend) -> Client {
+        let rocket = rocket::build()
+            .mount("/8", routes())
+            .manage(create_pokemon_source(PokemonConfig {
+                source,
+                fixtures: None,
+                cache: CacheConfig::default(),
+            }))
+            .manage(CacheAdminToken(Some(ADMIN_TOKEN.to_owned())));
+
+        Client::tracked(rocket).unwrap()
+    }
+
+    #[rstest]
+    #[case("/8/weight/25", "6")]
+    #[case("/8/weight/143", "460")]
+    #[case("/8/drop/25", "84.10707461325713")]
+    #[case("/8/drop/1", "96.72313580524569")]
+    fn test_pokemon_routes(
+        #[values(PokemonBackend::Fixture, PokemonBackend::Mock)] source: PokemonBackend,
+        #[case] uri: &str,
+        #[case] expected: &str,
+    ) {
+        let client = client(source);
+        let response = client.get(uri).dispatch();
+
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.into_string().unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case(PokemonBackend::Fixture)]
+    #[case(PokemonBackend::Mock)]
+    fn test_pokemon_not_found(#[case] source: PokemonBackend) {
+        let client = client(source);
+
+        for uri in ["/8/weight/9999", "/8/drop/9999", "/8/weight/missingno"] {
+            let response = client.get(uri).dispatch();
+            let id = uri.rsplit('/').next().unwrap();
+
+            assert_eq!(response.status(), Status::NotFound);
+            assert_eq!(
+                response.into_string().unwrap(),
+                format!("Pokémon {id} was not found")
+            );
+        }
+    }
+
+    /// Serves `response` to every request, like a PokeAPI that is down or has changed.
+    fn broken_pokeapi(response: &'static str, cache: &Path) -> CachedRustemon {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let base = format!("http://{}/api/v2/", listener.local_addr().unwrap());
+
+        thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let mut request_line = String::new();
+                let _read = BufReader::new(&stream).read_line(&mut request_line);
+                let _written = stream.write_all(response.as_bytes());
+            }
+        });
+
+        init_rustemon_client(
+            &CacheConfig {
+                mode: Some(RustemonCacheMode::NoStore),
+                path: Some(cache.to_owned()),
+            },
+            &base,
+            RustemonCacheMode::NoStore,
+        )
+    }
+
+    #[rocket::async_test]
+    async fn test_pokemon_bad_gateway() {
+        let responses = [
+            "HTTP/1.1 503 Service Unavailable\r\nContent-Type: text/html\r\n\
+             Content-Length: 13\r\nConnection: close\r\n\r\n<html></html>",
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\
+             Content-Length: 9\r\nConnection: close\r\n\r\n{\"name\": ",
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\
+             Content-Length: 15\r\nConnection: close\r\n\r\n{\"name\": \"mew\"}",
+        ];
+
+        let cache = tempfile::tempdir().unwrap();
+
+        for response in responses {
+            let source = broken_pokeapi(response, cache.path());
+
+            for id in [PokemonId::Number(151), PokemonId::Name("mew".to_owned())] {
+                let (status, _) = source.lookup(&id).await.unwrap_err();
+
+                assert_eq!(status, Status::BadGateway, "{response}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_pokemon_fixtures() {
+        let fixtures = PokemonFixtures::load(Path::new(relative!("fixtures/pokemon"))).unwrap();
+        let mut ids: Vec<i64> = fixtures.documents.keys().copied().collect();
+
+        ids.sort();
+
+        assert_eq!(ids, [1, 4, 7, 25, 133, 143, 150]);
+    }
+
+    fn pokemon(name: &str, height: i64, weight: i64) -> Pokemon {
+        Pokemon {
+            name: name.to_owned(),
+            height,
+            weight,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_drop_vacuum() {
+        let drop = DropPhysics::default()
+            .drop(&pokemon("pikachu", 4, 60))
+            .unwrap();
+
+        assert_eq!(drop.mass, 6.0);
+        assert_eq!(drop.velocity, f64::sqrt(196.5));
+        assert_eq!(drop.time, f64::sqrt(20.0 / 9.825));
+        assert_eq!(drop.momentum, 84.10707461325713);
+        assert!((drop.energy - 589.5).abs() < 1e-9);
+        assert_eq!(drop.drag, None);
+    }
+
+    #[rstest]
+    #[case(10.0)]
+    #[case(1000.0)]
+    #[case(1e9)]
+    fn test_drop_drag(#[case] height: f64) {
+        let snorlax = pokemon("snorlax", 21, 4600);
+        let vacuum = DropPhysics {
+            height: Some(height),
+            ..Default::default()
+        }
+        .drop(&snorlax)
+        .unwrap();
+        let drop = DropPhysics {
+            height: Some(height),
+            drag: Some(true),
+            ..Default::default()
+        }
+        .drop(&snorlax)
+        .unwrap();
+        let drag = drop.drag.unwrap();
+
+        assert!(drop.velocity < vacuum.velocity);
+        assert!(drop.velocity <= drag.terminal_velocity);
+        assert!(drop.time > vacuum.time);
+        assert!(drop.time.is_finite());
+        assert_eq!(drag.coefficient, SPHERE_DRAG_COEFFICIENT);
+        assert!((drag.cross_section - std::f64::consts::PI * 1.05 * 1.05).abs() < 1e-12);
+        assert!((drop.energy - 0.5 * drop.mass * drop.velocity.powi(2)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_drop_drag_terminal() {
+        let drop = DropPhysics {
+            height: Some(1e6),
+            drag: Some(true),
+            ..Default::default()
+        }
+        .drop(&pokemon("eevee", 3, 65))
+        .unwrap();
+        let terminal = drop.drag.unwrap().terminal_velocity;
+
+        assert!((drop.velocity - terminal).abs() < 1e-9);
+        // Almost all of the fall is at terminal velocity.
+        assert!((drop.time - 1e6 / terminal).abs() / drop.time < 1e-3);
+    }
+
+    #[test]
+    fn test_drop_drag_pointlike() {
+        let drop = DropPhysics {
+            drag: Some(true),
+            ..Default::default()
+        }
+        .drop(&pokemon("missingno", 0, 60))
+        .unwrap();
+
+        assert_eq!(drop.velocity, f64::sqrt(196.5));
+    }
+
+    #[rstest]
+    #[case("/8/drop/25?planet=moon", "34.1525987298185")]
+    #[case("/8/drop/25?gravity=1.62", "34.1525987298185")]
+    #[case("/8/drop/25?height=40&planet=Earth", "168.21414922651425")]
+    fn test_drop_physics_route(#[case] uri: &str, #[case] expected: &str) {
+        let client = client(PokemonBackend::Fixture);
+        let response = client.get(uri).dispatch();
+
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.into_string().unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case("/8/drop/25?gravity=1&planet=mars", "Use either gravity or planet")]
+    #[case("/8/drop/25?height=-1", "height must be positive")]
+    #[case("/8/drop/25?gravity=0", "gravity must be positive")]
+    #[case("/8/drop/25?drag=true&air_density=-1", "air_density must be positive")]
+    #[case(
+        "/8/drop/25/breakdown?drag=true&drag_coefficient=0",
+        "drag_coefficient must be positive"
+    )]
+    fn test_drop_physics_error(#[case] uri: &str, #[case] message: &str) {
+        let client = client(PokemonBackend::Fixture);
+        let response = client.get(uri).dispatch();
+
+        assert_eq!(response.status(), Status::BadRequest);
+        assert_eq!(response.into_string().unwrap(), message);
+    }
+
+    #[test]
+    fn test_drop_breakdown_route() {
+        let client = client(PokemonBackend::Fixture);
+        let response = client.get("/8/drop/25/breakdown?drag=true").dispatch();
+        let breakdown: serde_json::Value = response.into_json().unwrap();
+
+        assert_eq!(breakdown["pokemon"], "pikachu");
+        assert_eq!(breakdown["mass"], 6.0);
+        assert_eq!(breakdown["height"], 10.0);
+        assert_eq!(breakdown["gravity"], 9.825);
+        assert_eq!(breakdown["drag"]["coefficient"], 0.47);
+        assert!(breakdown["velocity"].as_f64().unwrap() < f64::sqrt(196.5));
+
+        let response = client.get("/8/drop/25/breakdown").dispatch();
+        let breakdown: serde_json::Value = response.into_json().unwrap();
+
+        assert_eq!(breakdown["drag"], serde_json::Value::Null);
+        assert_eq!(breakdown["momentum"], 84.10707461325713);
+    }
+
+    #[rstest]
+    #[case("25", Some(PokemonId::Number(25)))]
+    #[case(" #025 ", Some(PokemonId::Number(25)))]
+    #[case("Pikachu", Some(PokemonId::Name("pikachu".to_owned())))]
+    #[case("Mr. Mime", Some(PokemonId::Name("mr-mime".to_owned())))]
+    #[case("Farfetch’d", Some(PokemonId::Name("farfetchd".to_owned())))]
+    #[case("Nidoran♀", Some(PokemonId::Name("nidoran-f".to_owned())))]
+    #[case("nidoran female", Some(PokemonId::Name("nidoran-f".to_owned())))]
+    #[case("Flabébé", Some(PokemonId::Name("flabebe".to_owned())))]
+    #[case("Type: Null", Some(PokemonId::Name("type-null".to_owned())))]
+    #[case("HO_OH", Some(PokemonId::Name("ho-oh".to_owned())))]
+    #[case("Deoxys", Some(PokemonId::Name("deoxys-normal".to_owned())))]
+    #[case("  ", None)]
+    #[case("-.-", None)]
+    fn test_pokemon_id(#[case] query: &str, #[case] expected: Option<PokemonId>) {
+        assert_eq!(PokemonId::parse(query), expected);
+    }
+
+    #[rstest]
+    #[case("/8/weight/Pikachu", "6")]
+    #[case("/8/weight/%20eevee", "6.5")]
+    #[case("/8/drop/PIKACHU", "84.10707461325713")]
+    fn test_pokemon_routes_by_name(
+        #[values(PokemonBackend::Fixture, PokemonBackend::Mock)] source: PokemonBackend,
+        #[case] uri: &str,
+        #[case] expected: &str,
+    ) {
+        let client = client(source);
+        let response = client.get(uri).dispatch();
+
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.into_string().unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case(PokemonBackend::Fixture)]
+    #[case(PokemonBackend::Mock)]
+    fn test_batch(#[case] source: PokemonBackend) {
+        let client = client(source);
+        let response = client
+            .post("/8/batch")
+            .json(&serde_json::json!({"pokemon": [25, "Eevee", "missingno", " "]}))
+            .dispatch();
+        let report: serde_json::Value = response.into_json().unwrap();
+
+        assert_eq!(report["failed"], 2);
+        assert_eq!(
+            report["results"][0],
+            serde_json::json!({
+                "query": 25,
+                "id": 25,
+                "name": "pikachu",
+                "weight": 6.0,
+                "momentum": 84.10707461325713,
+            })
+        );
+        assert_eq!(report["results"][1]["query"], "Eevee");
+        assert_eq!(report["results"][1]["id"], 133);
+        assert_eq!(report["results"][2]["query"], "missingno");
+        assert_eq!(report["results"][2]["status"], 404);
+        assert_eq!(
+            report["results"][3],
+            serde_json::json!({"query": " ", "status": 400, "error": "Invalid Pokémon"})
+        );
+    }
+
+    #[rstest]
+    #[case(serde_json::json!({"pokemon": vec![1; 101]}), "", "At most 100 Pokémon per batch")]
+    #[case(serde_json::json!({"pokemon": [1]}), "?height=0", "height must be positive")]
+    fn test_batch_error(
+        #[case] batch: serde_json::Value,
+        #[case] query: &str,
+        #[case] message: &str,
+    ) {
+        let client = client(PokemonBackend::Fixture);
+        let response = client
+            .post(format!("/8/batch{query}"))
+            .json(&batch)
+            .dispatch();
+
+        assert_eq!(response.status(), Status::BadRequest);
+        assert_eq!(response.into_string().unwrap(), message);
+    }
+
+    /// Records how many lookups are in flight at once.
+    #[derive(Default)]
+    struct SlowSource {
+        in_flight: AtomicUsize,
+        peak: Arc<AtomicUsize>,
+    }
+
+    #[rocket::async_trait]
+    impl PokemonSource for SlowSource {
+        async fn pokemon(&self, pokedex_number: i64) -> PokemonResult {
+            let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+
+            self.peak.fetch_max(in_flight, Ordering::SeqCst);
+            rocket::tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+
+            Ok(Pokemon {
+                id: pokedex_number,
+                weight: pokedex_number,
+                ..Default::default()
+            })
+        }
+
+        async fn pokemon_by_name(&self, name: &str) -> PokemonResult {
+            Err((Status::NotFound, format!("Pokémon {name} was not found")))
+        }
+    }
+
+    #[rstest]
+    #[case(None, DEFAULT_BATCH_CONCURRENCY)]
+    #[case(Some(3), 3)]
+    #[case(Some(0), 1)]
+    #[case(Some(1000), MAX_BATCH_CONCURRENCY)]
+    fn test_batch_concurrency(#[case] concurrency: Option<usize>, #[case] peak: usize) {
+        let source = SlowSource::default();
+        let source_peak = source.peak.clone();
+        let rocket = rocket::build()
+            .mount("/8", routes())
+            .manage::<Box<dyn PokemonSource>>(Box::new(source));
+        let client = Client::tracked(rocket).unwrap();
+        let response = client
+            .post("/8/batch")
+            .json(&serde_json::json!({"pokemon": (1..=40).collect::<Vec<_>>(), "concurrency": concurrency}))
+            .dispatch();
+        let report: serde_json::Value = response.into_json().unwrap();
+        let ids: Vec<_> = (report["results"].as_array().unwrap().iter())
+            .map(|item| item["id"].as_i64().unwrap())
+            .collect();
+
+        assert_eq!(ids, (1..=40).collect::<Vec<_>>());
+        assert_eq!(source_peak.load(Ordering::SeqCst), peak);
+    }
+
+    fn cached_client(cache: &Path, mode: RustemonCacheMode) -> Client {
+        let rocket = rocket::build()
+            .mount("/8", routes())
+            .manage(create_pokemon_source(PokemonConfig {
+                source: PokemonBackend::Mock,
+                fixtures: None,
+                cache: CacheConfig {
+                    mode: Some(mode),
+                    path: Some(cache.to_owned()),
+                },
+            }))
+            .manage(CacheAdminToken(Some(ADMIN_TOKEN.to_owned())));
+
+        Client::tracked(rocket).unwrap()
+    }
+
+    fn cache_stats(client: &Client) -> serde_json::Value {
+        (client.get("/8/cache").header(admin()).dispatch())
+            .into_json()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_cache_hits() {
+        let cache = tempfile::tempdir().unwrap();
+        let client = cached_client(cache.path(), RustemonCacheMode::ForceCache);
+        let stats = cache_stats(&client);
+
+        assert_eq!(stats["mode"], "force-cache");
+        assert_eq!(stats["path"], cache.path().to_str().unwrap());
+        assert_eq!(
+            (stats["hits"].as_u64(), stats["misses"].as_u64()),
+            (Some(0), Some(0))
+        );
+        assert_eq!(stats["entries"], 0);
+
+        for uri in ["/8/weight/25", "/8/drop/25", "/8/weight/pikachu"] {
+            assert_eq!(client.get(uri).dispatch().status(), Status::Ok);
+        }
+
+        let stats = cache_stats(&client);
+
+        assert_eq!(
+            (stats["hits"].as_u64(), stats["misses"].as_u64()),
+            (Some(1), Some(2))
+        );
+        assert_eq!(stats["entries"], 2);
+        assert!(stats["size"].as_u64().unwrap() > 0);
+    }
+
+    #[test]
+    fn test_cache_no_store() {
+        let cache = tempfile::tempdir().unwrap();
+        let client = cached_client(cache.path(), RustemonCacheMode::NoStore);
+
+        for _ in 0..2 {
+            assert_eq!(client.get("/8/weight/25").dispatch().status(), Status::Ok);
+        }
+
+        let stats = cache_stats(&client);
+
+        assert_eq!(
+            (stats["hits"].as_u64(), stats["misses"].as_u64()),
+            (Some(0), Some(2))
+        );
+        assert_eq!(stats["entries"], 0);
+    }
+
+    #[test]
+    fn test_cache_warm_and_purge() {
+        let cache = tempfile::tempdir().unwrap();
+        let client = cached_client(cache.path(), RustemonCacheMode::ForceCache);
+        let warm: serde_json::Value = (client
+            .post("/8/cache/warm?from=1&to=7")
+            .header(admin())
+            .dispatch())
+        .into_json()
+        .unwrap();
+        let failed: Vec<_> = (warm["failed"].as_array().unwrap().iter())
+            .map(|item| {
+                (
+                    item["query"].as_i64().unwrap(),
+                    item["status"].as_u64().unwrap(),
+                )
+            })
+            .collect();
+
+        assert_eq!(warm["warmed"], 3);
+        assert_eq!(failed, [(2, 404), (3, 404), (5, 404), (6, 404)]);
+
+        client.get("/8/weight/4").dispatch();
+        assert_eq!(cache_stats(&client)["hits"], 1);
+
+        let purge: serde_json::Value = (client.delete("/8/cache/4").header(admin()).dispatch())
+            .into_json()
+            .unwrap();
+
+        assert_eq!(purge["purged"], 1);
+        client.get("/8/weight/4").dispatch();
+        assert_eq!(cache_stats(&client)["hits"], 1);
+
+        let purge: serde_json::Value = client
+            .delete("/8/cache/150")
+            .header(admin())
+            .dispatch()
+            .into_json()
+            .unwrap();
+
+        assert_eq!(purge["purged"], 0);
+
+        let entries = cache_stats(&client)["entries"].as_u64().unwrap();
+        let purge: serde_json::Value = (client.delete("/8/cache").header(admin()).dispatch())
+            .into_json()
+            .unwrap();
+        let stats = cache_stats(&client);
+
+        assert!(entries >= 3);
+        assert_eq!(purge["purged"], entries);
+        assert_eq!(stats["entries"], 0);
+        assert_eq!(stats["size"], 0);
+    }
+
+    #[rstest]
+    #[case("/8/cache/warm?from=0&to=7", "Invalid range 0 to 7")]
+    #[case("/8/cache/warm?from=7&to=1", "Invalid range 7 to 1")]
+    #[case("/8/cache/warm?from=1&to=2001", "At most 2000 Pokémon per warm-up")]
+    fn test_cache_warm_error(#[case] uri: &str, #[case] message: &str) {
+        let cache = tempfile::tempdir().unwrap();
+        let client = cached_client(cache.path(), RustemonCacheMode::ForceCache);
+        let response = client.post(uri).header(admin()).dispatch();
+
+        assert_eq!(response.status(), Status::BadRequest);
+        assert_eq!(response.into_string().unwrap(), message);
+    }
+
+    #[test]
+    fn test_cache_fixture() {
+        let client = client(PokemonBackend::Fixture);
+
+        for response in [
+            client.get("/8/cache").header(admin()).dispatch(),
+            client
+                .post("/8/cache/warm?from=1&to=7")
+                .header(admin())
+                .dispatch(),
+            client.delete("/8/cache").header(admin()).dispatch(),
+        ] {
+            assert_eq!(response.status(), Status::NotFound);
+        }
+    }
+
+    #[rstest]
+    #[case(None, Status::Unauthorized, "A cache admin token is required")]
+    #[case(
+        Some("Basic let-me-in"),
+        Status::Unauthorized,
+        "A cache admin token is required"
+    )]
+    #[case(
+        Some("Bearer let-me-out"),
+        Status::Forbidden,
+        "Invalid cache admin token"
+    )]
+    #[case(
+        Some("Bearer let-me-in!"),
+        Status::Forbidden,
+        "Invalid cache admin token"
+    )]
+    fn test_cache_admin_token(
+        #[case] authorization: Option<&str>,
+        #[case] status: Status,
+        #[case] message: &str,
+    ) {
+        let cache = tempfile::tempdir().unwrap();
+        let client = cached_client(cache.path(), RustemonCacheMode::ForceCache);
+
+        for mut request in [
+            client.get("/8/cache"),
+            client.post("/8/cache/warm?from=1&to=7"),
+            client.delete("/8/cache"),
+            client.delete("/8/cache/4"),
+        ] {
+            if let Some(authorization) = authorization {
+                request = request.header(Header::new("Authorization", authorization.to_owned()));
+            }
+
+            let response = request.dispatch();
+
+            assert_eq!(response.status(), status);
+            assert_eq!(response.into_string().unwrap(), message);
+        }
+
+        assert_eq!(cache_stats(&client)["misses"], 0);
+    }
+
+    #[test]
+    fn test_cache_admin_disabled() {
+        let cache = tempfile::tempdir().unwrap();
+        let rocket = rocket::build()
+            .mount("/8", routes())
+            .manage(create_pokemon_source(PokemonConfig {
+                source: PokemonBackend::Mock,
+                fixtures: None,
+                cache: CacheConfig {
+                    mode: Some(RustemonCacheMode::ForceCache),
+                    path: Some(cache.path().to_owned()),
+                },
+            }))
+            .manage(CacheAdminToken(None));
+        let client = Client::tracked(rocket).unwrap();
+        let response = client.delete("/8/cache").header(admin()).dispatch();
+
+        assert_eq!(response.status(), Status::Forbidden);
+        assert_eq!(response.into_string().unwrap(), "Cache admin is disabled");
     }
 }
@@ -1,3 +1,4 @@
+use rocket::figment::Figment;
 use rocket::http::Status;
 use rocket::response::status;
 use rocket::{get, routes};
@@ -17,6 +18,19 @@ fn error() -> status::Custom<&'static str> {
     status::Custom(Status::InternalServerError, "")
 }
 
+/// Extract the config under `key`, or its default if the key is not set at all.
+fn extract_config<T>(figment: &Figment, key: &str) -> anyhow::Result<T>
+where
+    T: for<'de> serde::Deserialize<'de> + Default,
+{
+    match figment.find_value(key) {
+        Err(err) if err.missing() => Ok(T::default()),
+        _ => figment
+            .extract_inner(key)
+            .map_err(|err| anyhow::anyhow!("Invalid config for {key}: {err}")),
+    }
+}
+
 #[shuttle_runtime::main]
 async fn main(
     #[shuttle_shared_db::Postgres()] pool: PgPool,
@@ -26,7 +40,17 @@ async fn main(
         .get("GOOGLE_API_KEY")
         .ok_or_else(|| anyhow::anyhow!("Missing key GOOGLE_API_KEY"))?;
 
-    let rocket = rocket::build()
+    // Day 7 private cookies need a key, and Rocket refuses to launch without one in release.
+    let secret_key = secret_store
+        .get("ROCKET_SECRET_KEY")
+        .ok_or_else(|| anyhow::anyhow!("Missing key ROCKET_SECRET_KEY"))?;
+    // Without a token the day 8 cache admin routes refuse every request.
+    let cache_admin_token = secret_store.get("CACHE_ADMIN_TOKEN");
+    let figment = rocket::Config::figment().merge(("secret_key", secret_key));
+    let rocket = rocket::custom(figment);
+    let name_list_backend = extract_config(rocket.figment(), "name_lists")?;
+    let pokemon_config = extract_config(rocket.figment(), "pokemon")?;
+    let rocket = rocket
         .attach(Template::fairing())
         .mount("/", routes![index, error])
         .mount("/1", cch23::day_01::routes())
@@ -45,7 +69,9 @@ async fn main(
         .mount("/20", cch23::day_20::routes())
         .mount("/21", cch23::day_21::routes())
         .mount("/22", cch23::day_22::routes())
-        .manage(cch23::day_08::init_rustemon_client())
+        .manage(cch23::day_05::create_name_lists(name_list_backend))
+        .manage(cch23::day_08::create_pokemon_source(pokemon_config))
+        .manage(cch23::day_08::CacheAdminToken(cache_admin_token))
         .manage(cch23::day_12::create_storage())
         .manage(cch23::day_19::bird_app::create_app())
         .manage(cch23::day_21::create_google_maps_client(&google_api_key))